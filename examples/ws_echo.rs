@@ -0,0 +1,31 @@
+//! a websocket echo server to try websocket profiles against, e.g. `features/ws.yml`
+use anyhow::Result;
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::TcpListener;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:9001".to_string());
+    let listener = TcpListener::bind(&addr).await?;
+    println!("echoing on ws://{}", addr);
+    serve(listener).await
+}
+
+/// echo the text and binary messages of every connection, also used by the websocket tests
+pub async fn serve(listener: TcpListener) -> Result<()> {
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(async move {
+            let mut socket = tokio_tungstenite::accept_async(stream).await?;
+            while let Some(message) = socket.next().await {
+                let message = message?;
+                if message.is_text() || message.is_binary() {
+                    socket.send(message).await?;
+                }
+            }
+            anyhow::Ok(())
+        });
+    }
+}
@@ -1,3 +1,4 @@
+use anyhow::anyhow;
 use anyhow::Ok;
 use anyhow::Result;
 use atty::Stream;
@@ -6,8 +7,11 @@ use dialoguer::theme::ColorfulTheme;
 use dialoguer::Input;
 use dialoguer::MultiSelect;
 
-use netdiff::cli::{Action, Args, RunArgs};
-use netdiff::{handle_run_err, highlight_text};
+use netdiff::cli::{
+    Action, Args, DiffRunArgs, ExportArgs, ExportFormat, ImportCollectionArgs, ImportCurlArgs,
+    ImportHarArgs, ImportOpenapiArgs,
+};
+use netdiff::{handle_run_err, print_failures, print_yaml};
 use netdiff::{DiffConfig, DiffProfile, ExtraArgs, LoadConfig, RequestProfile, ResponseProfile};
 
 use std::io::stdout;
@@ -15,10 +19,15 @@ use std::io::Write;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = Args::parse();
+    let args = Args::<DiffRunArgs>::parse();
     let result = match args.action {
         Action::Run(args) => run(args).await,
         Action::Parse => parse().await,
+        Action::ImportCurl(args) => import_curl(args).await,
+        Action::Export(args) => export(args).await,
+        Action::ImportHar(args) => import_har(args).await,
+        Action::ImportOpenapi(args) => import_openapi(args).await,
+        Action::ImportCollection(args) => import_collection(args).await,
         _ => panic!("error"),
     };
     handle_run_err(result)
@@ -47,24 +56,74 @@ async fn parse() -> Result<()> {
     let res = ResponseProfile::new(skip_headers, vec![]);
     let profile = DiffProfile::new(req1, req2, res);
     let config = DiffConfig::new(vec![(name, profile)].into_iter().collect());
-    let result = serde_yaml::to_string(&config)?;
-
-    let mut stdout = stdout().lock();
+    print_yaml(&config)
+}
 
-    if atty::is(Stream::Stdout) {
-        write!(
-            stdout,
-            "======== Parse Yaml ========\n{}",
-            highlight_text(&result, "yaml", None)?
-        )?;
+async fn import_curl(args: ImportCurlArgs) -> Result<()> {
+    let profiles = if args.command.is_empty() && atty::is(Stream::Stdin) {
+        let theme = ColorfulTheme::default();
+        let curl1: String = Input::with_theme(&theme)
+            .with_prompt("curl1?")
+            .interact_text()?;
+        let curl2: String = Input::with_theme(&theme)
+            .with_prompt("curl2? (empty to copy curl1)")
+            .allow_empty(true)
+            .interact_text()?;
+        let mut profiles = vec![curl1.parse()?];
+        if !curl2.trim().is_empty() {
+            profiles.push(curl2.parse()?);
+        }
+        profiles
     } else {
-        write!(stdout, "{}", &result)?;
+        args.read_profiles()?
+    };
+
+    // a single command is used for both sides, edit req2 to point at the other environment
+    let mut profiles = profiles.into_iter();
+    let (req1, req2) = match (profiles.next(), profiles.next(), profiles.next()) {
+        (Some(req1), None, None) => (req1.clone(), req1),
+        (Some(req1), Some(req2), None) => (req1, req2),
+        (None, _, _) => return Err(anyhow!("no curl command found")),
+        _ => {
+            return Err(anyhow!(
+                "netdiff import-curl takes one or two curl commands"
+            ))
+        }
+    };
+    let profile = DiffProfile::new(req1, req2, ResponseProfile::default());
+    let config = DiffConfig::new(vec![(args.name, profile)].into_iter().collect());
+    print_yaml(&config)
+}
+
+async fn import_har(args: ImportHarArgs) -> Result<()> {
+    let text = args.read_har()?;
+    let config = DiffConfig::from_har(&text, &args.filter(), args.target.as_ref())?;
+    if config.profiles.is_empty() {
+        return Err(anyhow!("no request in {} matches the filters", args.file));
     }
+    print_yaml(&config)
+}
 
-    Ok(())
+async fn import_openapi(args: ImportOpenapiArgs) -> Result<()> {
+    let text = std::fs::read_to_string(&args.file)?;
+    let config = DiffConfig::from_openapi(&text, args.base.as_ref(), args.target.as_ref())?;
+    print_yaml(&config)
+}
+
+async fn import_collection(args: ImportCollectionArgs) -> Result<()> {
+    let text = std::fs::read_to_string(&args.file)?;
+    let config = DiffConfig::from_collection(
+        &text,
+        &args.collection_vars()?,
+        args.env_name.as_deref(),
+        args.target.as_ref(),
+    )?;
+    print_yaml(&config)
 }
 
-async fn run(args: RunArgs) -> Result<()> {
+async fn run(args: DiffRunArgs) -> Result<()> {
+    let repeat = args.repeat;
+    let args = args.run;
     let config_file = args.config.unwrap_or_else(|| "./default.yml".to_string());
     let config = DiffConfig::load_yaml(&config_file).await?;
 
@@ -72,9 +131,61 @@ async fn run(args: RunArgs) -> Result<()> {
         .get_profile(&args.profile)
         .ok_or_else(|| anyhow::anyhow!("proflie {} is error:{}", args.profile, config_file))?;
 
-    let extra_args = args.extra_params.into();
-    let diff_str = profile.diff(extra_args).await?;
+    let mut extra_args: ExtraArgs = args.extra_params.into();
+    extra_args.user_agent = args.user_agent;
+    if let Some(runs) = repeat.repeat {
+        let result = profile.repeat(&extra_args, runs).await?;
+        let mut stdout = stdout().lock();
+        write!(stdout, "{}", result.output)?;
+        if repeat.suggest {
+            writeln!(stdout, "======== suggested res ========")?;
+            write!(stdout, "{}", serde_yaml::to_string(&result.suggested)?)?;
+            for warning in &result.warnings {
+                writeln!(stdout, "# {}", warning)?;
+            }
+        }
+        drop(stdout);
+        return print_failures(&result.failures);
+    }
+    let result = profile.diff(extra_args).await?;
     let mut stdout = stdout().lock();
-    write!(stdout, "{}", diff_str)?;
+    write!(stdout, "{}", result.output)?;
+    if !result.failures.is_empty() && !result.output.ends_with('\n') {
+        writeln!(stdout)?;
+    }
+    drop(stdout);
+    print_failures(&result.failures)
+}
+
+async fn export(args: ExportArgs) -> Result<()> {
+    let args_run = args.run;
+    let config_file = args_run
+        .config
+        .unwrap_or_else(|| "./default.yml".to_string());
+    let config = DiffConfig::load_yaml(&config_file).await?;
+
+    let profile = config
+        .get_profile(&args_run.profile)
+        .ok_or_else(|| anyhow::anyhow!("proflie {} is error:{}", args_run.profile, config_file))?;
+
+    let mut extra_args: ExtraArgs = args_run.extra_params.into();
+    extra_args.user_agent = args_run.user_agent;
+
+    let mut stdout = stdout().lock();
+    for (name, req) in [("req1", &profile.req1), ("req2", &profile.req2)] {
+        let output = match args.format {
+            ExportFormat::Curl => {
+                format!("# {}\n{}\n", name, req.to_curl(&extra_args).await?).into_bytes()
+            }
+            ExportFormat::Httpie => {
+                format!("# {}\n{}\n", name, req.to_httpie(&extra_args).await?).into_bytes()
+            }
+            ExportFormat::RawHttp => req.to_raw_http(&extra_args).await?,
+        };
+        stdout.write_all(&output)?;
+        if args.format == ExportFormat::RawHttp {
+            writeln!(stdout)?;
+        }
+    }
     Ok(())
 }
@@ -1,3 +1,4 @@
+use anyhow::anyhow;
 use anyhow::Ok;
 use anyhow::Result;
 use atty::Stream;
@@ -5,12 +6,17 @@ use clap::Parser;
 use dialoguer::theme::ColorfulTheme;
 use dialoguer::Input;
 
-use netdiff::cli::{Action, Args, RunArgs};
-use netdiff::{get_body_text, get_header_text, get_status_text, handle_run_err, highlight_text};
-use netdiff::{LoadConfig, RequestConfig, RequestProfile};
+use netdiff::cli::{
+    Action, Args, ExportArgs, ExportFormat, ImportCollectionArgs, ImportCurlArgs, ImportHarArgs,
+    ImportOpenapiArgs, RunArgs,
+};
+use netdiff::{
+    get_header_text, get_redirect_text, get_status_text, handle_run_err, highlight_text,
+    print_failures, print_yaml,
+};
+use netdiff::{ExtraArgs, LoadConfig, RequestConfig, RequestProfile, ResponseProfile};
 
 use std::fmt::Write as _;
-use std::io::stdout;
 use std::io::Write as _;
 
 #[tokio::main]
@@ -19,6 +25,11 @@ async fn main() -> Result<(), anyhow::Error> {
     let result = match args.action {
         Action::Run(args) => run(args).await,
         Action::Parse => parse().await,
+        Action::ImportCurl(args) => import_curl(args).await,
+        Action::Export(args) => export(args).await,
+        Action::ImportHar(args) => import_har(args).await,
+        Action::ImportOpenapi(args) => import_openapi(args).await,
+        Action::ImportCollection(args) => import_collection(args).await,
         _ => panic!("error"),
     };
     handle_run_err(result)
@@ -36,21 +47,61 @@ async fn parse() -> Result<()> {
         .interact_text()?;
 
     let config = RequestConfig::new(vec![(name, profile)].into_iter().collect());
-    let result = serde_yaml::to_string(&config)?;
-
-    let mut stdout = stdout().lock();
+    print_yaml(&config)
+}
 
-    if atty::is(Stream::Stdout) {
-        write!(
-            stdout,
-            "======== Parse Yaml ========\n{}",
-            highlight_text(&result, "yaml", None)?
-        )?;
+async fn import_curl(args: ImportCurlArgs) -> Result<()> {
+    let profiles = if args.command.is_empty() && atty::is(Stream::Stdin) {
+        let command: String = Input::with_theme(&ColorfulTheme::default())
+            .with_prompt("curl?")
+            .interact_text()?;
+        vec![command.parse()?]
     } else {
-        write!(stdout, "{}", &result)?;
+        args.read_profiles()?
+    };
+
+    let profiles = match profiles.len() {
+        0 => return Err(anyhow!("no curl command found")),
+        1 => vec![(args.name, profiles.into_iter().next().unwrap())],
+        _ => profiles
+            .into_iter()
+            .enumerate()
+            .map(|(i, profile)| (format!("{}-{}", args.name, i + 1), profile))
+            .collect(),
+    };
+    let config = RequestConfig::new(profiles.into_iter().collect());
+    print_yaml(&config)
+}
+
+async fn import_har(args: ImportHarArgs) -> Result<()> {
+    if args.target.is_some() {
+        return Err(anyhow!("--target is only supported by netdiff"));
     }
+    let text = args.read_har()?;
+    let config = RequestConfig::from_har(&text, &args.filter())?;
+    if config.profiles.is_empty() {
+        return Err(anyhow!("no request in {} matches the filters", args.file));
+    }
+    print_yaml(&config)
+}
 
-    Ok(())
+async fn import_openapi(args: ImportOpenapiArgs) -> Result<()> {
+    if args.target.is_some() {
+        return Err(anyhow!("--target is only supported by netdiff"));
+    }
+    let text = std::fs::read_to_string(&args.file)?;
+    let config = RequestConfig::from_openapi(&text, args.base.as_ref())?;
+    print_yaml(&config)
+}
+
+async fn import_collection(args: ImportCollectionArgs) -> Result<()> {
+    if args.target.is_some() {
+        return Err(anyhow!("--target is only supported by netdiff"));
+    }
+    let text = std::fs::read_to_string(&args.file)?;
+    let config =
+        RequestConfig::from_collection(&text, &args.collection_vars()?, args.env_name.as_deref())?;
+    print_yaml(&config)
 }
 
 async fn run(args: RunArgs) -> Result<()> {
@@ -61,16 +112,38 @@ async fn run(args: RunArgs) -> Result<()> {
         .get_profile(&args.profile)
         .ok_or_else(|| anyhow::anyhow!("proflie {} is error:{}", args.profile, config_file))?;
 
-    let extra_args = args.extra_params.into();
-    let res = profile.send(&extra_args).await?.into_inner();
-    let status = get_status_text(&res)?;
-    let headers = get_header_text(&res, &[])?;
-    let body = get_body_text(res, &[]).await?;
+    let mut extra_args: ExtraArgs = args.extra_params.into();
+    extra_args.user_agent = args.user_agent;
+    if profile.grpc.is_some() {
+        let output = profile
+            .invoke(&extra_args, &ResponseProfile::default())
+            .await?;
+        let url = profile.get_url(&extra_args)?;
+        print!("send url: {}\n{}", url, output);
+        return Ok(());
+    }
+    if profile.websocket.is_some() {
+        let transcript = profile.converse(&extra_args, &[]).await?;
+        let url = profile.get_url(&extra_args)?;
+        print!("send url: {}\n{}", url, transcript);
+        return Ok(());
+    }
+    let res = profile.send(&extra_args).await?;
+    let attempts = res.attempts();
+    let mut failures = res.check(&profile.expect);
+    let redirects = get_redirect_text(res.redirects())?;
+    let status = get_status_text(res.response())?;
+    let headers = get_header_text(res.response(), &[])?;
+    let body = res.body_text().await?;
+    failures.extend(profile.expect.check_body(&body, false)?);
     let url = profile.get_url(&extra_args)?;
 
     let mut output = String::new();
-    write!(&mut output, "send url: {}\n", &url)?;
-    write!(&mut output, "{}", status)?;
+    writeln!(&mut output, "send url: {}", &url)?;
+    if attempts > 1 {
+        writeln!(&mut output, "attempts: {}", attempts)?;
+    }
+    write!(&mut output, "{}{}", redirects, status)?;
 
     if atty::is(Stream::Stdout) {
         write!(
@@ -86,5 +159,34 @@ async fn run(args: RunArgs) -> Result<()> {
     let stdout = std::io::stdout();
     let mut stdout = stdout.lock();
     write!(stdout, "{}", &output)?;
+    if !failures.is_empty() && !output.ends_with('\n') {
+        writeln!(stdout)?;
+    }
+    drop(stdout);
+    print_failures(&failures)
+}
+
+async fn export(args: ExportArgs) -> Result<()> {
+    let args_run = args.run;
+    let config_file = args_run
+        .config
+        .unwrap_or_else(|| "./default.yml".to_string());
+    let config = RequestConfig::load_yaml(&config_file).await?;
+
+    let profile = config
+        .get_profile(&args_run.profile)
+        .ok_or_else(|| anyhow::anyhow!("proflie {} is error:{}", args_run.profile, config_file))?;
+
+    let mut extra_args: ExtraArgs = args_run.extra_params.into();
+    extra_args.user_agent = args_run.user_agent;
+    let output = match args.format {
+        ExportFormat::Curl => format!("{}\n", profile.to_curl(&extra_args).await?).into_bytes(),
+        ExportFormat::Httpie => format!("{}\n", profile.to_httpie(&extra_args).await?).into_bytes(),
+        ExportFormat::RawHttp => profile.to_raw_http(&extra_args).await?,
+    };
+
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    stdout.write_all(&output)?;
     Ok(())
 }
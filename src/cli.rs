@@ -1,19 +1,35 @@
-use anyhow::{anyhow, Ok, Result};
-use clap::{Parser, Subcommand};
+use anyhow::{anyhow, Context, Ok, Result};
+use clap::{Parser, Subcommand, ValueEnum};
 
-use crate::ExtraArgs;
+use std::io::Read;
+use url::Url;
 
+use crate::{
+    parse_curl_args, parse_curl_commands, CollectionVars, ExtraArgs, HarFilter, RequestProfile,
+};
+
+/// `R` is what `run` takes, netdiff's adds the repeat flags
 #[derive(Debug, Parser, Clone)]
 #[clap(version,author,about,long_about = None)]
-pub struct Args {
+pub struct Args<R: clap::Args = RunArgs> {
     #[clap(subcommand)]
-    pub action: Action,
+    pub action: Action<R>,
 }
 #[derive(Debug, Subcommand, Clone)]
 #[non_exhaustive]
-pub enum Action {
-    Run(RunArgs),
-    Parse
+pub enum Action<R: clap::Args = RunArgs> {
+    Run(R),
+    Parse,
+    /// generate a profile from curl commands
+    ImportCurl(ImportCurlArgs),
+    /// print the request of a profile as a curl, httpie or raw http command
+    Export(ExportArgs),
+    /// generate profiles from a har capture
+    ImportHar(ImportHarArgs),
+    /// generate profiles from an OpenAPI 3 document
+    ImportOpenapi(ImportOpenapiArgs),
+    /// generate profiles from a Postman v2.1 collection or an Insomnia v4 export
+    ImportCollection(ImportCollectionArgs),
 }
 
 #[derive(Debug, Parser, Clone)]
@@ -21,11 +37,171 @@ pub struct RunArgs {
     #[clap(short, long, value_parser)]
     pub profile: String,
 
+    /// `key=value` query, `%key=value` header, `@key=value` body field, `@key=@path` file part
     #[clap(short,long,value_parser = parser_key_val , number_of_values = 1)]
     pub extra_params: Vec<KeyVal>,
 
     #[clap(short, long)]
     pub config: Option<String>,
+
+    /// override the user agent of every request, a full string or one of the presets:
+    /// chrome, firefox, safari, edge, iphone, android, googlebot, googlebot-mobile, bingbot, curl
+    #[clap(short, long)]
+    pub user_agent: Option<String>,
+}
+
+/// what netdiff run takes
+#[derive(Debug, Parser, Clone)]
+pub struct DiffRunArgs {
+    #[clap(flatten)]
+    pub run: RunArgs,
+
+    #[clap(flatten)]
+    pub repeat: RepeatArgs,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct RepeatArgs {
+    /// send each side this many times and report what varies between its runs instead of
+    /// diffing
+    #[clap(long)]
+    pub repeat: Option<usize>,
+
+    /// with --repeat, print the `res` that skips the varying body fields and headers
+    #[clap(long, requires = "repeat")]
+    pub suggest: bool,
+}
+
+#[derive(Debug, Parser, Clone)]
+#[clap(trailing_var_arg = true)]
+pub struct ImportCurlArgs {
+    /// name of the generated profile
+    #[clap(short, long, default_value = "default")]
+    pub name: String,
+
+    /// the curl command, read from stdin or prompted when omitted
+    #[clap(value_parser, multiple_values = true)]
+    pub command: Vec<String>,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct ImportHarArgs {
+    /// path of the har file
+    #[clap(value_parser)]
+    pub file: String,
+
+    /// only keep requests to these hosts or their subdomains
+    #[clap(long, number_of_values = 1)]
+    pub domain: Vec<String>,
+
+    /// only keep requests with these methods
+    #[clap(long, number_of_values = 1)]
+    pub method: Vec<String>,
+
+    /// only keep responses whose content type contains one of these, e.g. json
+    #[clap(long, number_of_values = 1)]
+    pub content_type: Vec<String>,
+
+    /// base url req2 is sent to, netdiff only
+    #[clap(short, long)]
+    pub target: Option<Url>,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct ImportOpenapiArgs {
+    /// path of the yaml or json document
+    #[clap(value_parser)]
+    pub file: String,
+
+    /// base url of the requests, defaults to the first server of the document
+    #[clap(short, long)]
+    pub base: Option<Url>,
+
+    /// base url req2 is sent to, netdiff only, req2 is a copy of req1 if not set
+    #[clap(short, long)]
+    pub target: Option<Url>,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct ImportCollectionArgs {
+    /// path of the collection or export file
+    #[clap(value_parser)]
+    pub file: String,
+
+    /// Postman environment file whose values fill in the `{{vars}}`
+    #[clap(long)]
+    pub env: Option<String>,
+
+    /// name of the Insomnia sub environment to use on top of the base one
+    #[clap(long)]
+    pub env_name: Option<String>,
+
+    /// set a variable, overrides the collection and the environment
+    #[clap(long = "var", value_parser = parser_var, number_of_values = 1)]
+    pub vars: Vec<(String, String)>,
+
+    /// base url req2 is sent to, netdiff only
+    #[clap(short, long)]
+    pub target: Option<Url>,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct ExportArgs {
+    #[clap(flatten)]
+    pub run: RunArgs,
+
+    #[clap(long = "as", value_enum, default_value = "curl")]
+    pub format: ExportFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Curl,
+    Httpie,
+    RawHttp,
+}
+
+impl ImportCurlArgs {
+    /// the profiles of the curl commands given as args, or read from stdin
+    pub fn read_profiles(&self) -> Result<Vec<RequestProfile>> {
+        if !self.command.is_empty() {
+            return parse_curl_args(&self.command);
+        }
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text)?;
+        parse_curl_commands(&text)
+    }
+}
+
+impl ImportHarArgs {
+    pub fn filter(&self) -> HarFilter {
+        HarFilter {
+            domains: self.domain.clone(),
+            methods: self.method.clone(),
+            content_types: self.content_type.clone(),
+        }
+    }
+
+    pub fn read_har(&self) -> Result<String> {
+        std::fs::read_to_string(&self.file)
+            .with_context(|| format!("failed to read har file: {}", self.file))
+    }
+}
+
+impl ImportCollectionArgs {
+    /// the variables of the environment file and of `--var`
+    pub fn collection_vars(&self) -> Result<CollectionVars> {
+        let mut vars = CollectionVars::default();
+        if let Some(path) = &self.env {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read environment file: {}", path))?;
+            vars.load_postman_env(&text)?;
+        }
+        for (k, v) in &self.vars {
+            vars.set(k, v);
+        }
+        Ok(vars)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -39,9 +215,17 @@ pub struct KeyVal {
 pub enum KeyValType {
     Headers,
     Body,
+    File,
     Query,
 }
 
+fn parser_var(s: &str) -> Result<(String, String)> {
+    let (k, v) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("not an effective value:{}", s))?;
+    Ok((k.trim().to_string(), v.to_string()))
+}
+
 pub fn parser_key_val(s: &str) -> Result<KeyVal> {
     let mut parts = s.splitn(2, "=");
     let (key, val) = (
@@ -57,6 +241,18 @@ pub fn parser_key_val(s: &str) -> Result<KeyVal> {
 
     let (key_type, key) = match key.chars().next() {
         Some('%') => (KeyValType::Headers, &key[1..]),
+        // `@key=@path` sends the file at `path` as a multipart part
+        Some('@') if val.starts_with('@') => {
+            let path = val[1..].trim_start();
+            if path.is_empty() {
+                return Err(anyhow!("not an effective value:{}", s));
+            }
+            return Ok(KeyVal {
+                key_type: KeyValType::File,
+                key: key[1..].to_string(),
+                value: path.to_string(),
+            });
+        }
         Some('@') => (KeyValType::Body, &key[1..]),
         Some(v) if v.is_ascii_alphabetic() => (KeyValType::Query, key),
         _ => return Err(anyhow!("not an effective value")),
@@ -73,11 +269,13 @@ impl From<Vec<KeyVal>> for ExtraArgs {
         let mut headers = vec![];
         let mut query = vec![];
         let mut body = vec![];
+        let mut files = vec![];
 
         for v in value {
             match v.key_type {
                 KeyValType::Headers => headers.push((v.key, v.value)),
                 KeyValType::Body => body.push((v.key, v.value)),
+                KeyValType::File => files.push((v.key, v.value)),
                 KeyValType::Query => query.push((v.key, v.value)),
             }
         }
@@ -86,6 +284,96 @@ impl From<Vec<KeyVal>> for ExtraArgs {
             headers,
             body,
             query,
+            files,
+            user_agent: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_val(key_type: KeyValType, key: &str, value: &str) -> KeyVal {
+        KeyVal {
+            key_type,
+            key: key.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn prefixes_pick_the_kind_of_extra_arg() {
+        assert_eq!(
+            parser_key_val("a=1").unwrap(),
+            key_val(KeyValType::Query, "a", "1")
+        );
+        assert_eq!(
+            parser_key_val("%x-token=abc").unwrap(),
+            key_val(KeyValType::Headers, "x-token", "abc")
+        );
+        assert_eq!(
+            parser_key_val("@name=bob").unwrap(),
+            key_val(KeyValType::Body, "name", "bob")
+        );
+        assert!(parser_key_val("1a=1").is_err());
+        assert!(parser_key_val("a").is_err());
+    }
+
+    #[test]
+    fn file_parts_are_at_key_equals_at_path() {
+        assert_eq!(
+            parser_key_val("@avatar=@/tmp/a b.png").unwrap(),
+            key_val(KeyValType::File, "avatar", "/tmp/a b.png")
+        );
+        // `key@path` is a query value with an `@` in it
+        assert_eq!(
+            parser_key_val("email=a@b.test").unwrap(),
+            key_val(KeyValType::Query, "email", "a@b.test")
+        );
+        assert!(parser_key_val("avatar@/tmp/a.png").is_err());
+        assert!(parser_key_val("@avatar=@").is_err());
+    }
+
+    #[test]
+    fn file_parts_go_to_extra_args_files() {
+        let args: ExtraArgs = vec![
+            parser_key_val("@avatar=@a.png").unwrap(),
+            parser_key_val("@name=bob").unwrap(),
+        ]
+        .into();
+        assert_eq!(args.files, [("avatar".to_string(), "a.png".to_string())]);
+        assert_eq!(args.body, [("name".to_string(), "bob".to_string())]);
+    }
+
+    #[test]
+    fn repeat_flags_belong_to_netdiff_run_only() {
+        let args =
+            Args::<DiffRunArgs>::try_parse_from(["netdiff", "run", "-p", "a", "--repeat", "3"])
+                .unwrap();
+        let Action::Run(run) = args.action else {
+            panic!("not run");
+        };
+        assert_eq!(
+            (run.run.profile.as_str(), run.repeat.repeat),
+            ("a", Some(3))
+        );
+
+        for args in [
+            &["netdiff", "run", "-p", "a", "--suggest"][..],
+            &["netdiff", "export", "-p", "a", "--repeat", "3"],
+        ] {
+            assert!(
+                Args::<DiffRunArgs>::try_parse_from(args).is_err(),
+                "{:?}",
+                args
+            );
+        }
+        for args in [
+            &["netreq", "run", "-p", "a", "--repeat", "3"][..],
+            &["netreq", "export", "-p", "a", "--repeat", "3"],
+        ] {
+            assert!(Args::<RunArgs>::try_parse_from(args).is_err(), "{:?}", args);
         }
     }
 }
@@ -0,0 +1,209 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+use mime::Mime;
+use sha2::{Digest, Sha256};
+
+use super::BodyFormat;
+
+const BYTES_PER_ROW: usize = 16;
+/// rows of context around a differing row
+const CONTEXT_ROWS: usize = 1;
+const MAX_DIFF_ROWS: usize = 32;
+/// how much of a body without a content type is looked at to tell it's binary
+const SNIFF_LEN: usize = 8192;
+
+/// images, audio, video, fonts, archives and anything that isn't utf-8 text
+pub fn is_binary(content_type: Option<&Mime>, bytes: &[u8]) -> bool {
+    if let Some(mime) = content_type {
+        if BodyFormat::from_mime(mime).is_some() {
+            return false;
+        }
+        match (mime.type_().as_str(), mime.subtype().as_str()) {
+            ("text", _) => return false,
+            ("image" | "audio" | "video" | "font", _) => return true,
+            ("application", "octet-stream" | "pdf" | "zip" | "gzip" | "x-protobuf") => return true,
+            _ => {}
+        }
+    }
+    let head = &bytes[..bytes.len().min(SNIFF_LEN)];
+    if head.contains(&0) {
+        return true;
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => false,
+        // a multi-byte char cut off at the end of the sniffed part
+        Err(e) => e.error_len().is_some(),
+    }
+}
+
+/// size, hash and the image format and dimensions of a binary body
+pub fn binary_summary(bytes: &[u8]) -> Result<String> {
+    let mut output = String::new();
+    writeln!(output, "binary body")?;
+    writeln!(output, "size: {} bytes", bytes.len())?;
+    writeln!(output, "sha256: {:x}", Sha256::digest(bytes))?;
+    if let Ok(kind) = imagesize::image_type(bytes) {
+        writeln!(output, "image: {}", format!("{:?}", kind).to_lowercase())?;
+        if let Ok(size) = imagesize::blob_size(bytes) {
+            writeln!(output, "dimensions: {}x{}", size.width, size.height)?;
+        }
+    }
+    Ok(output)
+}
+
+/// hex dumps of the rows of two bodies that differ, with a row of context around them
+pub fn diff_binary(bytes1: &[u8], bytes2: &[u8]) -> (String, String) {
+    let rows = bytes1.len().max(bytes2.len()).div_ceil(BYTES_PER_ROW);
+    let row = |bytes: &[u8], i: usize| -> Vec<u8> {
+        let start = (i * BYTES_PER_ROW).min(bytes.len());
+        let end = (start + BYTES_PER_ROW).min(bytes.len());
+        bytes[start..end].to_vec()
+    };
+    let differing: Vec<usize> = (0..rows)
+        .filter(|i| row(bytes1, *i) != row(bytes2, *i))
+        .collect();
+    let mut shown: Vec<usize> = vec![];
+    for i in differing.iter().take(MAX_DIFF_ROWS) {
+        let start = i.saturating_sub(CONTEXT_ROWS);
+        let end = (i + CONTEXT_ROWS).min(rows - 1);
+        for j in start..=end {
+            if shown.last().is_none_or(|last| *last < j) {
+                shown.push(j);
+            }
+        }
+    }
+    let dump = |bytes: &[u8]| {
+        let mut output = String::new();
+        let mut last = None;
+        for i in &shown {
+            if last.is_some_and(|last| last + 1 != *i) {
+                output.push_str("...\n");
+            }
+            output.push_str(&hex_row(i * BYTES_PER_ROW, &row(bytes, *i)));
+            last = Some(*i);
+        }
+        if differing.len() > MAX_DIFF_ROWS {
+            let _ = writeln!(
+                output,
+                "... {} more differing rows",
+                differing.len() - MAX_DIFF_ROWS
+            );
+        }
+        output
+    };
+    (dump(bytes1), dump(bytes2))
+}
+
+/// `00000010  de ad be ef ...  |....|`
+fn hex_row(offset: usize, bytes: &[u8]) -> String {
+    let hex: Vec<String> = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    let ascii: String = bytes
+        .iter()
+        .map(|b| match b {
+            0x20..=0x7e => *b as char,
+            _ => '.',
+        })
+        .collect();
+    format!(
+        "{:08x}  {:<width$}  |{}|\n",
+        offset,
+        hex.join(" "),
+        ascii,
+        width = BYTES_PER_ROW * 3 - 1
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mime(s: &str) -> Mime {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn binary_bodies_by_content_type_and_bytes() {
+        assert!(is_binary(Some(&mime("image/png")), b"text"));
+        assert!(is_binary(Some(&mime("application/octet-stream")), b"text"));
+        assert!(!is_binary(Some(&mime("application/json")), b"\0"));
+        assert!(!is_binary(Some(&mime("text/csv")), &[0xff, 0xfe]));
+        assert!(is_binary(None, b"abc\0def"));
+        assert!(is_binary(None, &[0xff, 0xfe, 0x41]));
+        assert!(!is_binary(None, "héllo".as_bytes()));
+        // a multi-byte char cut off by the sniffed part is still text
+        let mut text = "a".repeat(SNIFF_LEN - 1).into_bytes();
+        text.extend("é".as_bytes());
+        assert!(!is_binary(None, &text));
+    }
+
+    #[test]
+    fn summaries_include_image_dimensions() {
+        let gif = b"GIF89a\x02\x00\x03\x00\x80\x00\x00\xff\xff\xff\x00\x00\x00!\xf9\x04\x00\x00\x00\x00\x00,\x00\x00\x00\x00\x02\x00\x03\x00\x00\x02\x02D\x01\x00;";
+        let summary = binary_summary(gif).unwrap();
+        assert!(
+            summary.contains(&format!("size: {} bytes", gif.len())),
+            "{}",
+            summary
+        );
+        assert!(
+            summary.contains("image: gif\ndimensions: 2x3\n"),
+            "{}",
+            summary
+        );
+        let summary = binary_summary(&[0, 1, 2]).unwrap();
+        assert!(!summary.contains("image"), "{}", summary);
+    }
+
+    #[test]
+    fn hex_rows_are_padded() {
+        assert_eq!(
+            hex_row(0x10, b"a\0"),
+            format!("00000010  61 00{}  |a.|\n", " ".repeat(42))
+        );
+    }
+
+    #[test]
+    fn only_differing_rows_and_their_context_are_dumped() {
+        let bytes1 = vec![0u8; 16 * 8];
+        let mut bytes2 = bytes1.clone();
+        bytes2[16 * 5 + 3] = 1;
+        let (dump1, dump2) = diff_binary(&bytes1, &bytes2);
+        let offsets =
+            |dump: &str| -> Vec<String> { dump.lines().map(|l| l[..8].to_string()).collect() };
+        assert_eq!(offsets(&dump1), ["00000040", "00000050", "00000060"]);
+        assert_eq!(offsets(&dump2), offsets(&dump1));
+        assert!(dump2.lines().nth(1).unwrap().contains(" 00 00 00 01 00"));
+    }
+
+    #[test]
+    fn rows_stay_aligned_when_lengths_differ() {
+        let bytes1 = vec![b'a'; 16 * 4];
+        let bytes2 = [&[b'a'; 16][..], &[b'b'; 16]].concat();
+        let (dump1, dump2) = diff_binary(&bytes1, &bytes2);
+        assert_eq!(dump1.lines().count(), 4);
+        assert_eq!(dump2.lines().count(), 4);
+        for (l1, l2) in dump1.lines().zip(dump2.lines()) {
+            assert_eq!(l1[..8], l2[..8]);
+        }
+        // rows past the end of the shorter body are empty
+        assert!(dump2.ends_with(&format!("00000030  {}  ||\n", " ".repeat(47))));
+    }
+
+    #[test]
+    fn gaps_between_differing_rows_are_marked() {
+        let bytes1 = vec![0u8; 16 * 10];
+        let mut bytes2 = bytes1.clone();
+        bytes2[0] = 1;
+        bytes2[16 * 9] = 1;
+        let (dump, _) = diff_binary(&bytes1, &bytes2);
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[2], "...");
+    }
+
+    #[test]
+    fn equal_bodies_have_no_rows() {
+        assert_eq!(diff_binary(b"abc", b"abc"), (String::new(), String::new()));
+    }
+}
@@ -0,0 +1,396 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use mime::Mime;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+/// body of a request, `body` is json unless `body_type` says otherwise
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestBody {
+    /// any json value, encoded as form if the content type says so
+    Json(Value),
+    /// an object sent as `application/x-www-form-urlencoded`
+    Form(Value),
+    Text(String),
+    Xml(String),
+    /// path of a file sent as is
+    File(String),
+    /// binary data in base64
+    Base64(String),
+    /// parts sent as `multipart/form-data`
+    Multipart(Vec<MultipartPart>),
+}
+
+/// a text field or a file of a multipart body
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MultipartPart {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub value: Option<String>,
+    /// path of the file sent in this part
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub file: Option<String>,
+    /// defaults to the file name of `file`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub filename: Option<String>,
+    /// defaults to a guess from the extension of `file`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub content_type: Option<String>,
+}
+
+/// how the `body` of a profile is sent
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BodyType {
+    Json,
+    Form,
+    Text,
+    Xml,
+    File,
+    Base64,
+    Multipart,
+}
+
+/// the `body` and `body_type` keys of a profile
+#[derive(Serialize, Deserialize, Default)]
+struct BodyFields {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    body: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    body_type: Option<BodyType>,
+}
+
+pub fn deserialize_body<'de, D>(deserializer: D) -> Result<Option<RequestBody>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let BodyFields { body, body_type } = BodyFields::deserialize(deserializer)?;
+    let (body, body_type) = match (body, body_type) {
+        (None, None) => return Ok(None),
+        (None, Some(_)) => return Err(serde::de::Error::custom("body_type 需要同时设置 body")),
+        (Some(body), body_type) => (body, body_type.unwrap_or(BodyType::Json)),
+    };
+    let text = |body: Value| match body {
+        Value::String(s) => Ok(s),
+        _ => Err(serde::de::Error::custom(format!(
+            "body_type {:?} 的 body 必须是字符串",
+            body_type
+        ))),
+    };
+    Ok(Some(match body_type {
+        BodyType::Json => RequestBody::Json(body),
+        BodyType::Form => RequestBody::Form(body),
+        BodyType::Text => RequestBody::Text(text(body)?),
+        BodyType::Xml => RequestBody::Xml(text(body)?),
+        BodyType::File => RequestBody::File(text(body)?),
+        BodyType::Base64 => RequestBody::Base64(text(body)?),
+        BodyType::Multipart => {
+            RequestBody::Multipart(serde_json::from_value(body).map_err(serde::de::Error::custom)?)
+        }
+    }))
+}
+
+/// a json body is written as a plain `body`, any other along with its `body_type`
+pub fn serialize_body<S>(body: &Option<RequestBody>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let (body, body_type) = match body {
+        None => (None, None),
+        Some(RequestBody::Json(v)) => (Some(v.clone()), None),
+        Some(RequestBody::Form(v)) => (Some(v.clone()), Some(BodyType::Form)),
+        Some(RequestBody::Text(s)) => (Some(Value::String(s.clone())), Some(BodyType::Text)),
+        Some(RequestBody::Xml(s)) => (Some(Value::String(s.clone())), Some(BodyType::Xml)),
+        Some(RequestBody::File(s)) => (Some(Value::String(s.clone())), Some(BodyType::File)),
+        Some(RequestBody::Base64(s)) => (Some(Value::String(s.clone())), Some(BodyType::Base64)),
+        Some(RequestBody::Multipart(parts)) => (
+            Some(serde_json::to_value(parts).map_err(serde::ser::Error::custom)?),
+            Some(BodyType::Multipart),
+        ),
+    };
+    BodyFields { body, body_type }.serialize(serializer)
+}
+
+impl Default for RequestBody {
+    fn default() -> Self {
+        RequestBody::Json(serde_json::json!({}))
+    }
+}
+
+impl RequestBody {
+    /// content type sent when the profile doesn't set one
+    pub fn default_content_type(&self) -> Mime {
+        match self {
+            RequestBody::Json(_) => mime::APPLICATION_JSON,
+            RequestBody::Form(_) => mime::APPLICATION_WWW_FORM_URLENCODED,
+            RequestBody::Text(_) => mime::TEXT_PLAIN_UTF_8,
+            RequestBody::Xml(_) => "application/xml".parse().unwrap(),
+            RequestBody::File(path) => guess_content_type(path),
+            RequestBody::Base64(_) => mime::APPLICATION_OCTET_STREAM,
+            RequestBody::Multipart(_) => mime::MULTIPART_FORM_DATA,
+        }
+    }
+
+    /// the structured value that `ExtraArgs::body` can override
+    pub fn fields_mut(&mut self) -> Option<&mut Value> {
+        match self {
+            RequestBody::Json(v) | RequestBody::Form(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        match self {
+            RequestBody::Form(v) if !v.is_object() => Err(anyhow!(
+                "body.form: {} 不是一个对象",
+                serde_yaml::to_string(v)?
+            )),
+            RequestBody::File(path) if !Path::new(path).is_file() => {
+                Err(anyhow!("body.file: {} 文件不存在", path))
+            }
+            RequestBody::Base64(data) => {
+                decode_base64(data).context("body.base64 不是有效的 base64")?;
+                Ok(())
+            }
+            RequestBody::Multipart(parts) => parts.iter().try_for_each(MultipartPart::validate),
+            _ => Ok(()),
+        }
+    }
+
+    /// parts of a multipart body, the fields of an object body become text parts
+    pub fn to_multipart(&self) -> Result<Vec<MultipartPart>> {
+        match self {
+            RequestBody::Multipart(parts) => Ok(parts.clone()),
+            RequestBody::Json(Value::Object(obj)) | RequestBody::Form(Value::Object(obj)) => {
+                Ok(obj
+                    .iter()
+                    .map(|(k, v)| MultipartPart::text(k, &value_to_text(v)))
+                    .collect())
+            }
+            _ => Err(anyhow!(
+                "multipart/form-data 只支持对象或 multipart 类型的 body"
+            )),
+        }
+    }
+
+    /// raw bytes of a text, xml, file or base64 body, `None` for structured bodies
+    pub async fn raw_bytes(&self) -> Result<Option<Vec<u8>>> {
+        match self {
+            RequestBody::Json(_) | RequestBody::Form(_) | RequestBody::Multipart(_) => Ok(None),
+            RequestBody::Text(s) | RequestBody::Xml(s) => Ok(Some(s.as_bytes().to_vec())),
+            RequestBody::File(path) => {
+                Ok(Some(tokio::fs::read(path).await.with_context(|| {
+                    format!("failed to read body file: {}", path)
+                })?))
+            }
+            RequestBody::Base64(data) => Ok(Some(decode_base64(data)?)),
+        }
+    }
+}
+
+fn guess_content_type(path: &str) -> Mime {
+    let ext = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    match ext.as_str() {
+        "json" => mime::APPLICATION_JSON,
+        "xml" => "application/xml".parse().unwrap(),
+        "ndjson" | "jsonl" => "application/x-ndjson".parse().unwrap(),
+        "txt" => mime::TEXT_PLAIN_UTF_8,
+        "html" | "htm" => mime::TEXT_HTML_UTF_8,
+        "csv" => mime::TEXT_CSV_UTF_8,
+        "png" => mime::IMAGE_PNG,
+        "jpg" | "jpeg" => mime::IMAGE_JPEG,
+        "gif" => mime::IMAGE_GIF,
+        "svg" => mime::IMAGE_SVG,
+        "pdf" => mime::APPLICATION_PDF,
+        _ => mime::APPLICATION_OCTET_STREAM,
+    }
+}
+
+impl MultipartPart {
+    pub fn text(name: &str, value: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            value: Some(value.to_string()),
+            file: None,
+            filename: None,
+            content_type: None,
+        }
+    }
+
+    pub fn file(name: &str, path: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            value: None,
+            file: Some(path.to_string()),
+            filename: None,
+            content_type: None,
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        match (&self.value, &self.file) {
+            (Some(_), None) => Ok(()),
+            (None, Some(path)) if Path::new(path).is_file() => Ok(()),
+            (None, Some(path)) => Err(anyhow!("multipart {}: {} 文件不存在", self.name, path)),
+            _ => Err(anyhow!(
+                "multipart {}: value 和 file 必须且只能设置一个",
+                self.name
+            )),
+        }
+    }
+}
+
+/// a boundary that is unlikely to appear in the parts
+pub fn gen_boundary() -> String {
+    let bytes: [u8; 16] = rand::random();
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("netdiff-boundary-{}", hex)
+}
+
+/// encode the parts as a `multipart/form-data` body
+pub async fn encode_multipart(parts: &[MultipartPart], boundary: &str) -> Result<Vec<u8>> {
+    let mut body = vec![];
+    for part in parts {
+        body.extend(format!("--{}\r\n", boundary).as_bytes());
+        let name = escape_disposition(&part.name);
+        match &part.file {
+            Some(path) => {
+                let filename = part.filename.clone().unwrap_or_else(|| {
+                    Path::new(path)
+                        .file_name()
+                        .map(|f| f.to_string_lossy().to_string())
+                        .unwrap_or_default()
+                });
+                let content_type = part
+                    .content_type
+                    .clone()
+                    .unwrap_or_else(|| guess_content_type(path).to_string());
+                body.extend(
+                    format!(
+                        "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+                        name,
+                        escape_disposition(&filename),
+                        content_type
+                    )
+                    .as_bytes(),
+                );
+                body.extend(
+                    tokio::fs::read(path)
+                        .await
+                        .with_context(|| format!("failed to read multipart file: {}", path))?,
+                );
+            }
+            None => {
+                body.extend(
+                    format!("Content-Disposition: form-data; name=\"{}\"\r\n", name).as_bytes(),
+                );
+                if let Some(content_type) = &part.content_type {
+                    body.extend(format!("Content-Type: {}\r\n", content_type).as_bytes());
+                }
+                body.extend(b"\r\n");
+                body.extend(part.value.as_deref().unwrap_or_default().as_bytes());
+            }
+        }
+        body.extend(b"\r\n");
+    }
+    body.extend(format!("--{}--\r\n", boundary).as_bytes());
+    Ok(body)
+}
+
+/// percent-encode what would end the quoted string or the header line, as browsers do
+fn escape_disposition(s: &str) -> String {
+    s.replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// base64 that may be wrapped over several lines
+fn decode_base64(data: &str) -> Result<Vec<u8>> {
+    let data: String = data.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+    Ok(STANDARD.decode(data)?)
+}
+
+pub(super) fn value_to_text(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn wrapped_base64_decodes() {
+        let body = RequestBody::Base64("aGVs\nbG8g\r\n d29y bGQ=\n".to_string());
+        assert!(body.validate().is_ok());
+        let bytes = body.raw_bytes().await.unwrap();
+        assert_eq!(bytes.as_deref(), Some(&b"hello world"[..]));
+        assert!(RequestBody::Base64("not base64!".to_string())
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn body_type_picks_the_variant() {
+        let body: BodyFields = serde_yaml::from_str("{body: '<a/>', body_type: xml}").unwrap();
+        assert_eq!(body.body_type, Some(BodyType::Xml));
+
+        #[derive(Deserialize, Debug)]
+        struct Profile {
+            #[serde(flatten, deserialize_with = "deserialize_body")]
+            body: Option<RequestBody>,
+        }
+        let parse = |yaml: &str| serde_yaml::from_str::<Profile>(yaml).map(|p| p.body);
+        assert_eq!(
+            parse("body: {a: 1}").unwrap(),
+            Some(RequestBody::Json(serde_json::json!({"a": 1})))
+        );
+        assert_eq!(
+            parse("{body: hi, body_type: text}").unwrap(),
+            Some(RequestBody::Text("hi".to_string()))
+        );
+        assert!(parse("{body: {a: 1}, body_type: text}").is_err());
+        assert!(parse("body_type: text").is_err());
+    }
+
+    #[tokio::test]
+    async fn multipart_names_cant_break_the_header() {
+        let parts = vec![MultipartPart::text("a\"b\r\nX-Injected: 1", "v")];
+        let body = encode_multipart(&parts, "b").await.unwrap();
+        let body = String::from_utf8(body).unwrap();
+        assert_eq!(
+            body,
+            "--b\r\nContent-Disposition: form-data; name=\"a%22b%0D%0AX-Injected: 1\"\r\n\r\nv\r\n--b--\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn file_parts_carry_filename_and_content_type() {
+        let path = std::env::temp_dir().join(format!("netdiff-part-{}.json", std::process::id()));
+        std::fs::write(&path, "{}").unwrap();
+        let path = path.to_string_lossy().into_owned();
+        let parts = vec![MultipartPart::file("doc", &path)];
+        let body = encode_multipart(&parts, "b").await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let body = String::from_utf8(body).unwrap();
+        let filename = Path::new(&path).file_name().unwrap().to_string_lossy();
+        assert!(body.contains(&format!(
+            "name=\"doc\"; filename=\"{}\"\r\nContent-Type: application/json\r\n\r\n{{}}\r\n",
+            filename
+        )));
+    }
+
+    #[test]
+    fn boundaries_are_random() {
+        assert_ne!(gen_boundary(), gen_boundary());
+    }
+}
@@ -0,0 +1,789 @@
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::{
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    HeaderMap, HeaderName, HeaderValue, Method,
+};
+use serde_json::{json, Map, Value};
+use url::Url;
+
+use super::{
+    curl::data_to_body,
+    har::{retarget_url, unique_name},
+    DiffConfig, DiffProfile, GraphqlRequest, MultipartPart, RequestBody, RequestConfig,
+    RequestProfile, ResponseProfile,
+};
+
+/// variables of a collection, later sources override earlier ones
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CollectionVars {
+    vars: HashMap<String, String>,
+}
+
+/// a request before variables are substituted, shared by both formats
+#[derive(Debug, Default)]
+struct RawRequest {
+    name: String,
+    method: String,
+    url: String,
+    query: Vec<(String, String)>,
+    headers: Vec<(String, String)>,
+    body: Option<RawBody>,
+    auth: Option<Value>,
+    /// environments of the enclosing folders
+    vars: CollectionVars,
+}
+
+#[derive(Debug)]
+enum RawBody {
+    /// raw text with the content type implied by the editor mode
+    Text(String, Option<&'static str>),
+    Form(Vec<(String, String)>),
+    Multipart(Vec<MultipartPart>),
+    File(String),
+    Graphql(String, String),
+}
+
+impl CollectionVars {
+    pub fn set(&mut self, key: &str, value: &str) {
+        self.vars.insert(key.to_string(), value.to_string());
+    }
+
+    /// `values` of a Postman environment export, disabled values are skipped
+    pub fn load_postman_env(&mut self, text: &str) -> Result<()> {
+        let env: Value =
+            serde_json::from_str(text).context("不是有效的 Postman environment 文件")?;
+        for v in array(&env, "values") {
+            if v.get("enabled").and_then(|e| e.as_bool()) != Some(false) {
+                self.set(str_of(v, "key"), &value_text(v.get("value")));
+            }
+        }
+        Ok(())
+    }
+
+    fn extend_from_object(&mut self, data: Option<&Value>, prefix: &str) {
+        let Some(Value::Object(obj)) = data else {
+            return;
+        };
+        for (k, v) in obj {
+            let key = format!("{}{}", prefix, k);
+            match v {
+                Value::Object(_) => self.extend_from_object(Some(v), &format!("{}.", key)),
+                v => self.set(&key, &value_text(Some(v))),
+            }
+        }
+    }
+
+    /// replace `{{name}}` and insomnia's `{{ _.name }}`, unknown variables are kept as is
+    pub fn substitute(&self, text: &str) -> String {
+        let mut output = text.to_string();
+        // variables may refer to other variables
+        for _ in 0..8 {
+            let next = self.substitute_once(&output);
+            if next == output {
+                break;
+            }
+            output = next;
+        }
+        output
+    }
+
+    fn substitute_once(&self, text: &str) -> String {
+        let mut output = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            let Some(end) = rest[start..].find("}}") else {
+                break;
+            };
+            let name = rest[start + 2..start + end].trim();
+            let name = name.strip_prefix("_.").unwrap_or(name);
+            output.push_str(&rest[..start]);
+            match self.vars.get(name) {
+                Some(value) => output.push_str(value),
+                None => output.push_str(&rest[start..start + end + 2]),
+            }
+            rest = &rest[start + end + 2..];
+        }
+        output.push_str(rest);
+        output
+    }
+}
+
+/// profiles of a Postman v2.1 collection or an Insomnia v4 export, named after their folders
+pub fn parse_collection(
+    text: &str,
+    vars: &CollectionVars,
+    insomnia_env: Option<&str>,
+) -> Result<Vec<(String, RequestProfile)>> {
+    let root: Value = serde_json::from_str(text).context("不是有效的 collection 文件")?;
+    let (requests, file_vars) = if root.get("_type").and_then(|t| t.as_str()) == Some("export") {
+        insomnia_requests(&root, insomnia_env)?
+    } else if root.pointer("/info/schema").is_some() {
+        postman_requests(&root)?
+    } else {
+        return Err(anyhow!(
+            "只支持 Postman v2.1 collection 和 Insomnia v4 export"
+        ));
+    };
+    let mut names = HashSet::new();
+    let mut profiles = vec![];
+    for req in requests {
+        // folder variables override the file ones, the command line ones override both
+        let mut req_vars = file_vars.clone();
+        req_vars.vars.extend(req.vars.vars.clone());
+        req_vars.vars.extend(vars.vars.clone());
+        let profile = req
+            .to_profile(&req_vars)
+            .with_context(|| format!("request: {}", req.name))?;
+        profiles.push((unique_name(&mut names, slug(&req.name)), profile));
+    }
+    Ok(profiles)
+}
+
+fn postman_requests(root: &Value) -> Result<(Vec<RawRequest>, CollectionVars)> {
+    let schema = root
+        .pointer("/info/schema")
+        .and_then(|s| s.as_str())
+        .unwrap_or_default();
+    if !schema.contains("v2.1") && !schema.contains("v2.0") {
+        return Err(anyhow!("不支持的 Postman collection 版本: {}", schema));
+    }
+    let mut vars = CollectionVars::default();
+    for v in array(root, "variable") {
+        if v.get("disabled").and_then(|d| d.as_bool()) != Some(true) {
+            vars.set(str_of(v, "key"), &value_text(v.get("value")));
+        }
+    }
+    let mut requests = vec![];
+    postman_items(root, "", root.get("auth"), &mut requests);
+    Ok((requests, vars))
+}
+
+/// walk the folders, the auth of a folder applies to the requests inside unless they set their own
+fn postman_items(
+    folder: &Value,
+    prefix: &str,
+    auth: Option<&Value>,
+    requests: &mut Vec<RawRequest>,
+) {
+    for item in array(folder, "item") {
+        let name = join_name(prefix, str_of(item, "name"));
+        if item.get("item").is_some() {
+            postman_items(item, &name, item.get("auth").or(auth), requests);
+            continue;
+        }
+        let Some(req) = item.get("request") else {
+            continue;
+        };
+        let (url, query) = match req.get("url") {
+            Some(Value::String(url)) => (url.clone(), vec![]),
+            Some(url) => {
+                // the query of `raw` also holds the disabled params, `query` tells them apart
+                let raw = str_of(url, "raw");
+                let base = match url.get("query") {
+                    Some(_) => raw.split_once('?').map_or(raw, |(base, _)| base),
+                    None => raw,
+                };
+                let query = array(url, "query")
+                    .iter()
+                    .filter(|q| q.get("disabled").and_then(|d| d.as_bool()) != Some(true))
+                    .map(|q| (str_of(q, "key").to_string(), value_text(q.get("value"))))
+                    .collect();
+                (base.to_string(), query)
+            }
+            None => (String::new(), vec![]),
+        };
+
+        requests.push(RawRequest {
+            name,
+            method: match str_of(req, "method") {
+                "" => "GET".to_string(),
+                m => m.to_string(),
+            },
+            url,
+            query,
+            headers: enabled_pairs(array(req, "header"), "key", "disabled"),
+            body: req.get("body").and_then(postman_body),
+            auth: req.get("auth").or(auth).cloned(),
+            vars: CollectionVars::default(),
+        });
+    }
+}
+
+fn postman_body(body: &Value) -> Option<RawBody> {
+    match str_of(body, "mode") {
+        "raw" => {
+            let content_type = match body
+                .pointer("/options/raw/language")
+                .and_then(|l| l.as_str())
+            {
+                Some("json") => Some("application/json"),
+                Some("xml") => Some("application/xml"),
+                Some("html") => Some("text/html"),
+                Some("javascript") => Some("application/javascript"),
+                _ => Some("text/plain"),
+            };
+            Some(RawBody::Text(str_of(body, "raw").to_string(), content_type))
+        }
+        "urlencoded" => Some(RawBody::Form(enabled_pairs(
+            array(body, "urlencoded"),
+            "key",
+            "disabled",
+        ))),
+        "formdata" => Some(RawBody::Multipart(
+            array(body, "formdata")
+                .iter()
+                .filter(|p| p.get("disabled").and_then(|d| d.as_bool()) != Some(true))
+                .map(|p| {
+                    let name = str_of(p, "key");
+                    let mut part = match str_of(p, "type") {
+                        "file" => MultipartPart::file(name, &value_text(p.get("src"))),
+                        _ => MultipartPart::text(name, &value_text(p.get("value"))),
+                    };
+                    part.content_type = p
+                        .get("contentType")
+                        .and_then(|c| c.as_str())
+                        .map(String::from);
+                    part
+                })
+                .collect(),
+        )),
+        "file" => body
+            .pointer("/file/src")
+            .and_then(|s| s.as_str())
+            .map(|s| RawBody::File(s.to_string())),
+        "graphql" => Some(RawBody::Graphql(
+            body.pointer("/graphql/query")
+                .and_then(|q| q.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            body.pointer("/graphql/variables")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string(),
+        )),
+        _ => None,
+    }
+}
+
+fn insomnia_requests(
+    root: &Value,
+    env_name: Option<&str>,
+) -> Result<(Vec<RawRequest>, CollectionVars)> {
+    let resources = array(root, "resources");
+    let by_id: HashMap<&str, &Value> = resources.iter().map(|r| (str_of(r, "_id"), r)).collect();
+    let of_type = |ty: &'static str| resources.iter().filter(move |r| str_of(r, "_type") == ty);
+
+    // the base environment hangs off the workspace, sub environments off the base one
+    let mut vars = CollectionVars::default();
+    let base_envs: Vec<&Value> = of_type("environment")
+        .filter(|e| {
+            by_id.get(str_of(e, "parentId")).map(|p| str_of(p, "_type")) == Some("workspace")
+        })
+        .collect();
+    for env in &base_envs {
+        vars.extend_from_object(env.get("data"), "");
+    }
+    if let Some(name) = env_name {
+        let env = of_type("environment")
+            .find(|e| {
+                str_of(e, "name") == name
+                    && base_envs
+                        .iter()
+                        .all(|b| str_of(b, "_id") != str_of(e, "_id"))
+            })
+            .ok_or_else(|| anyhow!("Insomnia environment {} 不存在", name))?;
+        vars.extend_from_object(env.get("data"), "");
+    }
+
+    let mut requests = vec![];
+    for req in of_type("request") {
+        // folder names and environments, innermost last
+        let mut folders = vec![];
+        let mut parent = by_id.get(str_of(req, "parentId"));
+        while let Some(p) = parent.filter(|p| str_of(p, "_type") == "request_group") {
+            folders.push(*p);
+            parent = by_id.get(str_of(p, "parentId"));
+        }
+        folders.reverse();
+        let mut folder_vars = CollectionVars::default();
+        for folder in &folders {
+            folder_vars.extend_from_object(folder.get("environment"), "");
+        }
+        let name = folders
+            .iter()
+            .map(|f| str_of(f, "name"))
+            .chain([str_of(req, "name")])
+            .fold(String::new(), |acc, n| join_name(&acc, n));
+
+        let body = req.get("body").and_then(|b| {
+            let mime_type = str_of(b, "mimeType");
+            match mime_type {
+                "" if b.get("text").is_none() => None,
+                "application/x-www-form-urlencoded" => Some(RawBody::Form(enabled_pairs(
+                    array(b, "params"),
+                    "name",
+                    "disabled",
+                ))),
+                "multipart/form-data" => Some(RawBody::Multipart(
+                    array(b, "params")
+                        .iter()
+                        .filter(|p| p.get("disabled").and_then(|d| d.as_bool()) != Some(true))
+                        .map(|p| match str_of(p, "type") {
+                            "file" => MultipartPart::file(str_of(p, "name"), str_of(p, "fileName")),
+                            _ => {
+                                MultipartPart::text(str_of(p, "name"), &value_text(p.get("value")))
+                            }
+                        })
+                        .collect(),
+                )),
+                "application/octet-stream" => b
+                    .get("fileName")
+                    .and_then(|f| f.as_str())
+                    .map(|f| RawBody::File(f.to_string())),
+                "application/graphql" => {
+                    let payload: Value =
+                        serde_json::from_str(str_of(b, "text")).unwrap_or_default();
+                    Some(RawBody::Graphql(
+                        str_of(&payload, "query").to_string(),
+                        payload
+                            .get("variables")
+                            .map(|v| v.to_string())
+                            .unwrap_or_default(),
+                    ))
+                }
+                _ => Some(RawBody::Text(str_of(b, "text").to_string(), None)),
+            }
+        });
+
+        let auth = req
+            .get("authentication")
+            .filter(|a| a.get("disabled").and_then(|d| d.as_bool()) != Some(true))
+            .and_then(insomnia_auth);
+
+        requests.push(RawRequest {
+            name,
+            method: match str_of(req, "method") {
+                "" => "GET".to_string(),
+                m => m.to_string(),
+            },
+            url: str_of(req, "url").to_string(),
+            query: enabled_pairs(array(req, "parameters"), "name", "disabled"),
+            headers: enabled_pairs(array(req, "headers"), "name", "disabled"),
+            body,
+            auth,
+            vars: folder_vars,
+        });
+    }
+    Ok((requests, vars))
+}
+
+impl RawRequest {
+    fn to_profile(&self, vars: &CollectionVars) -> Result<RequestProfile> {
+        // every field is checked, not only the url, an unset variable must not be sent as is
+        let unresolved = RefCell::new(BTreeSet::new());
+        let sub = |s: &str| {
+            let text = vars.substitute(s);
+            unresolved.borrow_mut().extend(unresolved_vars(&text));
+            text
+        };
+        let url = sub(&self.url);
+        let url = if url.contains("://") {
+            url
+        } else {
+            format!("http://{}", url)
+        };
+        let mut url = Url::parse(&url)?;
+
+        let mut params = Map::new();
+        for (k, v) in url.query_pairs() {
+            params.insert(k.to_string(), Value::String(v.into_owned()));
+        }
+        url.set_query(None);
+        for (k, v) in &self.query {
+            params.insert(sub(k), Value::String(sub(v)));
+        }
+
+        let mut headers = HeaderMap::new();
+        for (k, v) in &self.headers {
+            headers.append(
+                HeaderName::from_bytes(sub(k).as_bytes())?,
+                HeaderValue::from_str(&sub(v))?,
+            );
+        }
+        if let Some(auth) = &self.auth {
+            apply_auth(auth, &sub, &mut headers, &mut params)?;
+        }
+
+        let mut graphql = None;
+        let body = match &self.body {
+            None => None,
+            Some(RawBody::Text(text, content_type)) if !text.is_empty() => {
+                if let Some(content_type) = content_type {
+                    headers
+                        .entry(CONTENT_TYPE)
+                        .or_insert(HeaderValue::from_static(content_type));
+                }
+                Some(data_to_body(sub(text), &mut headers))
+            }
+            Some(RawBody::Text(..)) => None,
+            Some(RawBody::Form(pairs)) => Some(RequestBody::Form(Value::Object(
+                pairs
+                    .iter()
+                    .map(|(k, v)| (sub(k), Value::String(sub(v))))
+                    .collect(),
+            ))),
+            Some(RawBody::Multipart(parts)) => Some(RequestBody::Multipart(
+                parts
+                    .iter()
+                    .map(|p| {
+                        let mut part = p.clone();
+                        part.name = sub(&part.name);
+                        part.value = part.value.as_deref().map(sub);
+                        part.file = part.file.as_deref().map(sub);
+                        part
+                    })
+                    .collect(),
+            )),
+            Some(RawBody::File(path)) => Some(RequestBody::File(sub(path))),
+            Some(RawBody::Graphql(query, variables)) => {
+                let variables = match sub(variables).trim() {
+                    "" => Map::new(),
+                    v => serde_json::from_str(v).context("graphql variables 不是有效的 json")?,
+                };
+                graphql = Some(GraphqlRequest {
+                    query: Some(sub(query)),
+                    variables,
+                    ..Default::default()
+                });
+                None
+            }
+        };
+
+        let unresolved = unresolved.into_inner();
+        if !unresolved.is_empty() {
+            let names: Vec<String> = unresolved
+                .iter()
+                .map(|n| format!("{{{{{}}}}}", n))
+                .collect();
+            return Err(anyhow!(
+                "{} 中有未定义的变量 {}，请用 --env 或 --var 设置",
+                self.url,
+                names.join(", ")
+            ));
+        }
+
+        let method: Method = self.method.to_uppercase().parse()?;
+        let mut profile =
+            RequestProfile::new(method, url, Value::Object(params), headers, body, None);
+        profile.graphql = graphql;
+        Ok(profile)
+    }
+}
+
+/// insomnia's auth has its own shape, turned into the postman one
+fn insomnia_auth(auth: &Value) -> Option<Value> {
+    let kv = |pairs: &[(&str, &str)]| {
+        Value::Array(
+            pairs
+                .iter()
+                .map(|(k, v)| json!({ "key": k, "value": v }))
+                .collect(),
+        )
+    };
+    let auth = match str_of(auth, "type") {
+        "bearer" => json!({
+            "type": "bearer",
+            "bearer": kv(&[("token", str_of(auth, "token")), ("prefix", str_of(auth, "prefix"))]),
+        }),
+        "basic" => json!({
+            "type": "basic",
+            "basic": kv(&[
+                ("username", str_of(auth, "username")),
+                ("password", str_of(auth, "password")),
+            ]),
+        }),
+        "apikey" => {
+            let place = match str_of(auth, "addTo") {
+                "queryParams" => "query",
+                _ => "header",
+            };
+            json!({
+                "type": "apikey",
+                "apikey": kv(&[
+                    ("key", str_of(auth, "key")),
+                    ("value", str_of(auth, "value")),
+                    ("in", place),
+                ]),
+            })
+        }
+        _ => return None,
+    };
+    Some(auth)
+}
+
+/// bearer, basic and api key auth, other kinds have to be set up by hand
+fn apply_auth(
+    auth: &Value,
+    sub: &dyn Fn(&str) -> String,
+    headers: &mut HeaderMap,
+    params: &mut Map<String, Value>,
+) -> Result<()> {
+    let ty = str_of(auth, "type");
+    let field = |key: &str| {
+        array(auth, ty)
+            .iter()
+            .find(|f| str_of(f, "key") == key)
+            .map(|f| sub(&value_text(f.get("value"))))
+            .unwrap_or_default()
+    };
+    if headers.contains_key(AUTHORIZATION) {
+        return Ok(());
+    }
+    match ty {
+        "bearer" => {
+            let prefix = match field("prefix") {
+                p if p.is_empty() => "Bearer".to_string(),
+                p => p,
+            };
+            headers.insert(
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("{} {}", prefix, field("token")))?,
+            );
+        }
+        "basic" => {
+            let token = STANDARD.encode(format!("{}:{}", field("username"), field("password")));
+            headers.insert(
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("Basic {}", token))?,
+            );
+        }
+        "apikey" => match field("in").as_str() {
+            "query" => {
+                params.insert(field("key"), Value::String(field("value")));
+            }
+            _ => {
+                headers.insert(
+                    HeaderName::from_bytes(field("key").as_bytes())?,
+                    HeaderValue::from_str(&field("value"))?,
+                );
+            }
+        },
+        _ => {}
+    }
+    Ok(())
+}
+
+/// the names of the `{{name}}` left after substitution, json such as `{{"a":1}}` isn't one
+fn unresolved_vars(text: &str) -> Vec<String> {
+    let mut names = vec![];
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + end].trim();
+        if !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || "_-.$".contains(c))
+        {
+            names.push(name.to_string());
+        }
+        rest = &rest[start + end + 2..];
+    }
+    names
+}
+
+fn array<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value
+        .get(key)
+        .and_then(|v| v.as_array())
+        .map(|v| v.as_slice())
+        .unwrap_or_default()
+}
+
+fn str_of<'a>(value: &'a Value, key: &str) -> &'a str {
+    value.get(key).and_then(|v| v.as_str()).unwrap_or_default()
+}
+
+/// strings are taken as is, other values are written as json
+fn value_text(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Null) | None => String::new(),
+        Some(v) => v.to_string(),
+    }
+}
+
+fn enabled_pairs(items: &[Value], key: &str, disabled: &str) -> Vec<(String, String)> {
+    items
+        .iter()
+        .filter(|i| i.get(disabled).and_then(|d| d.as_bool()) != Some(true))
+        .map(|i| (str_of(i, key).to_string(), value_text(i.get("value"))))
+        .collect()
+}
+
+fn join_name(prefix: &str, name: &str) -> String {
+    match prefix {
+        "" => name.to_string(),
+        prefix => format!("{}/{}", prefix, name),
+    }
+}
+
+/// `users-get-user` for `Users/Get user`
+fn slug(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.to_lowercase().chars() {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    match slug.trim_end_matches('-') {
+        "" => "request".to_string(),
+        slug => slug.to_string(),
+    }
+}
+
+impl DiffConfig {
+    /// build a config from a Postman or Insomnia collection, req2 is sent to `target` if set
+    pub fn from_collection(
+        text: &str,
+        vars: &CollectionVars,
+        insomnia_env: Option<&str>,
+        target: Option<&Url>,
+    ) -> Result<Self> {
+        let profiles = parse_collection(text, vars, insomnia_env)?
+            .into_iter()
+            .map(|(name, req1)| {
+                let mut req2 = req1.clone();
+                if let Some(target) = target {
+                    req2.url = retarget_url(&req1.url, target);
+                }
+                (
+                    name,
+                    DiffProfile::new(req1, req2, ResponseProfile::default()),
+                )
+            })
+            .collect();
+        Ok(Self::new(profiles))
+    }
+}
+
+impl RequestConfig {
+    /// build a config from a Postman or Insomnia collection
+    pub fn from_collection(
+        text: &str,
+        vars: &CollectionVars,
+        insomnia_env: Option<&str>,
+    ) -> Result<Self> {
+        Ok(Self::new(
+            parse_collection(text, vars, insomnia_env)?
+                .into_iter()
+                .collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn postman(request: Value) -> String {
+        json!({
+            "info": { "schema": "https://schema.getpostman.com/json/collection/v2.1.0/collection.json" },
+            "item": [{ "name": "get user", "request": request }],
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn unresolved_variables_are_reported_outside_the_url() {
+        let text = postman(json!({
+            "method": "POST",
+            "url": "https://a.test/users",
+            "header": [{ "key": "x-token", "value": "{{token}}" }],
+            "body": { "mode": "raw", "raw": "{\"id\": \"{{ user_id }}\"}" },
+        }));
+        let err = parse_collection(&text, &CollectionVars::default(), None).unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(message.contains("{{token}}"), "{}", message);
+        assert!(message.contains("{{user_id}}"), "{}", message);
+
+        let mut vars = CollectionVars::default();
+        vars.set("token", "t");
+        vars.set("user_id", "1");
+        assert!(parse_collection(&text, &vars, None).is_ok());
+    }
+
+    #[test]
+    fn json_bodies_are_not_variables() {
+        assert!(unresolved_vars(r#"{"a":{"b":{"c":1}}}"#).is_empty());
+        assert_eq!(unresolved_vars("{{ _.host }}/x"), ["_.host"]);
+    }
+
+    #[test]
+    fn insomnia_auth_becomes_postman_auth() {
+        let auth = insomnia_auth(&json!({
+            "type": "apikey",
+            "key": "k",
+            "value": "v",
+            "addTo": "queryParams",
+        }))
+        .unwrap();
+        assert_eq!(
+            auth,
+            json!({
+                "type": "apikey",
+                "apikey": [
+                    { "key": "key", "value": "k" },
+                    { "key": "value", "value": "v" },
+                    { "key": "in", "value": "query" },
+                ],
+            })
+        );
+        assert!(insomnia_auth(&json!({ "type": "oauth2" })).is_none());
+    }
+
+    #[test]
+    fn query_values_stay_strings() {
+        let text = postman(json!({
+            "method": "GET",
+            "url": {
+                "raw": "https://a.test/x?v=1.10&e=1e3",
+                "query": [
+                    { "key": "v", "value": "1.10" },
+                    { "key": "e", "value": "1e3" },
+                    { "key": "id", "value": "12345678901234567890123" },
+                    { "key": "n", "value": "null" },
+                ],
+            },
+        }));
+        let profiles = parse_collection(&text, &CollectionVars::default(), None).unwrap();
+        assert_eq!(
+            profiles[0].1.params,
+            json!({ "v": "1.10", "e": "1e3", "id": "12345678901234567890123", "n": "null" })
+        );
+    }
+
+    #[test]
+    fn dedup_suffixes_dont_collide_with_request_names() {
+        let item = |name: &str| json!({ "name": name, "request": { "url": "https://a.test/" } });
+        let text = json!({
+            "info": { "schema": "https://schema.getpostman.com/json/collection/v2.1.0/collection.json" },
+            "item": [item("a"), item("a"), item("A 2")],
+        })
+        .to_string();
+        let names: Vec<String> = parse_collection(&text, &CollectionVars::default(), None)
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["a", "a-2", "a-2-2"]);
+    }
+}
@@ -0,0 +1,557 @@
+use std::collections::HashSet;
+
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::{
+    header::{AUTHORIZATION, CONTENT_TYPE, COOKIE, REFERER},
+    HeaderMap, HeaderName, HeaderValue, Method,
+};
+use serde_json::{json, Map, Value};
+use url::{form_urlencoded, Url};
+
+use super::{FollowRedirects, MultipartPart, RequestBody, RequestProfile};
+
+/// curl options that take a value, read from the next argument, after `=` or attached to a short flag
+const WITH_VALUE: &[&str] = &[
+    "--url",
+    "-X",
+    "--request",
+    "-H",
+    "--header",
+    "-d",
+    "--data",
+    "--data-ascii",
+    "--data-binary",
+    "--data-raw",
+    "--data-urlencode",
+    "--json",
+    "-F",
+    "--form",
+    "--form-string",
+    "-u",
+    "--user",
+    "-b",
+    "--cookie",
+    "-A",
+    "--user-agent",
+    "-e",
+    "--referer",
+    "--max-redirs",
+    "-m",
+    "--max-time",
+    "--retry",
+    "-x",
+    "--proxy",
+    "--resolve",
+    "--cacert",
+    "-E",
+    "--cert",
+    "--key",
+];
+
+/// curl options that take a value but have no meaning for a profile
+const IGNORED_WITH_VALUE: &[&str] = &[
+    "-o",
+    "--output",
+    "-w",
+    "--write-out",
+    "-c",
+    "--cookie-jar",
+    "-D",
+    "--dump-header",
+    "--connect-timeout",
+    "--retry-delay",
+    "--retry-max-time",
+    "-r",
+    "--range",
+    "--limit-rate",
+    "-Y",
+    "--speed-limit",
+    "-y",
+    "--speed-time",
+    "--max-filesize",
+    "--keepalive-time",
+    "--expect100-timeout",
+    "--trace",
+    "--trace-ascii",
+    "--stderr",
+];
+
+/// curl flags that don't change what is sent
+const IGNORED_FLAGS: &[&str] = &[
+    "-s",
+    "--silent",
+    "-S",
+    "--show-error",
+    "-L",
+    "--location",
+    "-v",
+    "--verbose",
+    "-i",
+    "--include",
+    "-f",
+    "--fail",
+    "--fail-with-body",
+    "-#",
+    "--progress-bar",
+    "--no-progress-meter",
+    "-N",
+    "--no-buffer",
+    "-O",
+    "--remote-name",
+    "--compressed",
+    "-g",
+    "--globoff",
+    "--path-as-is",
+    "-0",
+    "--http1.0",
+    "--http1.1",
+    "--http2",
+    "--http2-prior-knowledge",
+    "-4",
+    "--ipv4",
+    "-6",
+    "--ipv6",
+    "--tlsv1.2",
+    "--tlsv1.3",
+    "--tcp-nodelay",
+    "--no-keepalive",
+    "--retry-connrefused",
+    "--retry-all-errors",
+];
+
+#[derive(Debug, Default)]
+struct CurlCommand {
+    url: Option<String>,
+    method: Option<Method>,
+    headers: Vec<(String, String)>,
+    data: Vec<String>,
+    forms: Vec<String>,
+    json: bool,
+    get: bool,
+    head: bool,
+    user: Option<String>,
+    cookie: Option<String>,
+    user_agent: Option<String>,
+    referer: Option<String>,
+    insecure: bool,
+    max_redirs: Option<usize>,
+    max_time: Option<f64>,
+    retries: Option<u32>,
+    proxy: Option<String>,
+    resolve: Vec<String>,
+    cacert: Option<String>,
+    cert: Option<String>,
+    key: Option<String>,
+}
+
+impl RequestProfile {
+    /// build a profile from a curl command line, a bare url is accepted as well
+    pub fn from_curl(command: &str) -> Result<Self> {
+        let args = shell_words::split(command.trim()).context("curl 命令解析失败")?;
+        Self::from_curl_args(&args)
+    }
+
+    /// build a profile from the arguments of a curl command, with or without the leading `curl`
+    pub fn from_curl_args(args: &[String]) -> Result<Self> {
+        let (cmd, rest) = CurlCommand::parse(args)?;
+        if !rest.is_empty() {
+            return Err(anyhow!("只能有一个 curl 命令"));
+        }
+        cmd.into_profile()
+    }
+}
+
+/// parse every curl command found in `text`, each one starts with `curl`
+pub fn parse_curl_commands(text: &str) -> Result<Vec<RequestProfile>> {
+    let args = shell_words::split(text.trim()).context("curl 命令解析失败")?;
+    parse_curl_args(&args)
+}
+
+/// parse the already split arguments of one or more curl commands, a new command starts
+/// at a `curl` that is not the value of an option
+pub fn parse_curl_args(args: &[String]) -> Result<Vec<RequestProfile>> {
+    let mut profiles = vec![];
+    let mut rest = args;
+    while !rest.is_empty() {
+        let (cmd, next) = CurlCommand::parse(rest)?;
+        profiles.push(cmd.into_profile()?);
+        rest = next;
+    }
+    Ok(profiles)
+}
+
+impl CurlCommand {
+    /// parse one command, returning the arguments of the commands after it
+    fn parse(args: &[String]) -> Result<(Self, &[String])> {
+        let mut cmd = CurlCommand::default();
+        let args = match args.first() {
+            Some(first) if first == "curl" => &args[1..],
+            _ => args,
+        };
+        let mut iter = args.iter();
+
+        while let Some(arg) = iter.next() {
+            if arg == "curl" {
+                let rest = iter.as_slice();
+                return Ok((cmd, &args[args.len() - rest.len() - 1..]));
+            }
+            let options = match arg.strip_prefix("--") {
+                // `--flag=value` form of long options
+                Some(_) => match arg.split_once('=') {
+                    Some((flag, value)) => vec![(flag.to_string(), Some(value.to_string()))],
+                    None => vec![(arg.clone(), None)],
+                },
+                None if arg.starts_with('-') && arg.len() > 1 => short_options(arg),
+                None => {
+                    cmd.url = Some(arg.clone());
+                    continue;
+                }
+            };
+            for (flag, inline) in options {
+                let value = match inline {
+                    Some(_) if !takes_value(&flag) => {
+                        return Err(anyhow!("curl option {} doesn't take a value", flag))
+                    }
+                    Some(value) => Some(value),
+                    None if takes_value(&flag) => Some(
+                        iter.next()
+                            .cloned()
+                            .ok_or_else(|| anyhow!("curl option {} needs a value", flag))?,
+                    ),
+                    None => None,
+                };
+                cmd.apply(&flag, value)?;
+            }
+        }
+        Ok((cmd, &[]))
+    }
+
+    /// `value` is set for the options in `WITH_VALUE` and `IGNORED_WITH_VALUE`
+    fn apply(&mut self, flag: &str, value: Option<String>) -> Result<()> {
+        let value = move || value.ok_or_else(|| anyhow!("curl option {} needs a value", flag));
+        match flag {
+            "--url" => self.url = Some(value()?),
+            "-X" | "--request" => self.method = Some(value()?.parse()?),
+            "-H" | "--header" => {
+                let header = value()?;
+                let (k, v) = header
+                    .split_once(':')
+                    .ok_or_else(|| anyhow!("不是有效的 header: {}", header))?;
+                self.headers
+                    .push((k.trim().to_string(), v.trim().to_string()));
+            }
+            "-d" | "--data" | "--data-ascii" | "--data-binary" => {
+                let data = value()?;
+                self.data.push(match data.strip_prefix('@') {
+                    Some(path) => std::fs::read_to_string(path)
+                        .with_context(|| format!("failed to read curl data file: {}", path))?,
+                    None => data,
+                });
+            }
+            "--data-raw" => self.data.push(value()?),
+            "--data-urlencode" => self.data.push(urlencode_data(&value()?)?),
+            "--json" => {
+                self.data.push(value()?);
+                self.json = true;
+            }
+            "-F" | "--form" | "--form-string" => self.forms.push(value()?),
+            "-G" | "--get" => self.get = true,
+            "-I" | "--head" => self.head = true,
+            "-u" | "--user" => self.user = Some(value()?),
+            "-b" | "--cookie" => self.cookie = Some(value()?),
+            "-A" | "--user-agent" => self.user_agent = Some(value()?),
+            "-e" | "--referer" => self.referer = Some(value()?),
+            "-k" | "--insecure" => self.insecure = true,
+            "--max-redirs" => self.max_redirs = Some(value()?.parse()?),
+            "-m" | "--max-time" => self.max_time = Some(value()?.parse()?),
+            "--retry" => self.retries = Some(value()?.parse()?),
+            "-x" | "--proxy" => self.proxy = Some(value()?),
+            "--resolve" => self.resolve.push(value()?),
+            "--cacert" => self.cacert = Some(value()?),
+            "-E" | "--cert" => self.cert = Some(value()?),
+            "--key" => self.key = Some(value()?),
+            f if IGNORED_WITH_VALUE.contains(&f) || IGNORED_FLAGS.contains(&f) => {}
+            f => return Err(anyhow!("不支持的 curl 选项: {}", f)),
+        }
+        Ok(())
+    }
+
+    fn into_profile(self) -> Result<RequestProfile> {
+        let url = self
+            .url
+            .as_deref()
+            .ok_or_else(|| anyhow!("curl 命令中没有 url"))?;
+        let url = if url.contains("://") {
+            url.to_string()
+        } else {
+            format!("http://{}", url)
+        };
+        let mut url = Url::parse(&url)?;
+
+        let mut params = Map::new();
+        for (k, v) in url.query_pairs() {
+            params.insert(k.to_string(), Value::String(v.into_owned()));
+        }
+        url.set_query(None);
+
+        let mut headers = HeaderMap::new();
+        for (k, v) in &self.headers {
+            headers.append(
+                HeaderName::from_bytes(k.as_bytes())?,
+                HeaderValue::from_str(v)?,
+            );
+        }
+        if let Some(user) = &self.user {
+            let token = STANDARD.encode(user);
+            headers.insert(
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("Basic {}", token))?,
+            );
+        }
+        if let Some(cookie) = &self.cookie {
+            // a value without `=` is a cookie file for curl
+            if cookie.contains('=') {
+                headers.insert(COOKIE, HeaderValue::from_str(cookie)?);
+            }
+        }
+        if let Some(referer) = &self.referer {
+            headers.insert(REFERER, HeaderValue::from_str(referer)?);
+        }
+        if self.json {
+            headers
+                .entry(CONTENT_TYPE)
+                .or_insert(HeaderValue::from_static("application/json"));
+        }
+
+        let data = (!self.data.is_empty()).then(|| self.data.join("&"));
+        let mut body = None;
+        if self.get {
+            for (k, v) in form_urlencoded::parse(data.unwrap_or_default().as_bytes()) {
+                params.insert(k.to_string(), Value::String(v.into_owned()));
+            }
+        } else if !self.forms.is_empty() {
+            body = Some(RequestBody::Multipart(
+                self.forms
+                    .iter()
+                    .map(|f| parse_form(f))
+                    .collect::<Result<_>>()?,
+            ));
+        } else if let Some(data) = data {
+            body = Some(data_to_body(data, &mut headers));
+        }
+
+        let method = match (self.method, self.head) {
+            (Some(method), _) => method,
+            (None, true) => Method::HEAD,
+            (None, false) if body.is_some() => Method::POST,
+            _ => Method::GET,
+        };
+
+        let mut profile = RequestProfile::new(
+            method,
+            url,
+            Value::Object(params),
+            headers,
+            body,
+            self.user_agent,
+        );
+        profile.follow_redirects = self.max_redirs.map(FollowRedirects::Limit);
+        profile.retry.timeout = self.max_time.map(|secs| (secs * 1000.0) as u64);
+        profile.retry.retries = self.retries;
+        profile.route.proxy = self.proxy;
+        profile.route.resolve = self.resolve;
+        profile.tls.insecure = self.insecure.then_some(true);
+        profile.tls.ca_cert = self.cacert;
+        profile.tls.client_cert = self.cert;
+        profile.tls.client_key = self.key;
+        Ok(profile)
+    }
+}
+
+fn takes_value(flag: &str) -> bool {
+    WITH_VALUE.contains(&flag) || IGNORED_WITH_VALUE.contains(&flag)
+}
+
+/// the flags of a short option bundle like `-sSLk`, a flag taking a value ends the bundle
+/// and gets the rest of it, as in `-XPOST`
+fn short_options(arg: &str) -> Vec<(String, Option<String>)> {
+    let mut options = vec![];
+    for (i, c) in arg.char_indices().skip(1) {
+        let flag = format!("-{}", c);
+        if takes_value(&flag) {
+            let rest = &arg[i + c.len_utf8()..];
+            options.push((flag, (!rest.is_empty()).then(|| rest.to_string())));
+            break;
+        }
+        options.push((flag, None));
+    }
+    options
+}
+
+/// pick the body kind from the content type curl would send
+pub(super) fn data_to_body(data: String, headers: &mut HeaderMap) -> RequestBody {
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/x-www-form-urlencoded")
+        .to_string();
+
+    if content_type.contains("json") {
+        if let Ok(value) = serde_json::from_str::<Value>(&data) {
+            headers.remove(CONTENT_TYPE);
+            if content_type != "application/json" {
+                headers.insert(CONTENT_TYPE, HeaderValue::from_str(&content_type).unwrap());
+            }
+            return RequestBody::Json(value);
+        }
+    }
+
+    if content_type.starts_with("application/x-www-form-urlencoded") {
+        let pairs = form_urlencoded::parse(data.as_bytes()).collect::<Vec<_>>();
+        let keys = pairs.iter().map(|(k, _)| k).collect::<HashSet<_>>();
+        // repeated or nested keys don't round-trip through an object, keep the raw text
+        if keys.len() == pairs.len() && keys.iter().all(|k| !k.contains('[')) {
+            headers.remove(CONTENT_TYPE);
+            let form = pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), json!(v)))
+                .collect::<Map<_, _>>();
+            return RequestBody::Form(Value::Object(form));
+        }
+    }
+
+    headers
+        .entry(CONTENT_TYPE)
+        .or_insert(HeaderValue::from_str(&content_type).unwrap());
+    RequestBody::Text(data)
+}
+
+/// `name=value`, `name=@file;type=mime;filename=x` of `curl -F`
+fn parse_form(form: &str) -> Result<MultipartPart> {
+    let (name, value) = form
+        .split_once('=')
+        .ok_or_else(|| anyhow!("不是有效的 form: {}", form))?;
+    let mut fields = value.split(';');
+    let value = fields.next().unwrap_or_default();
+    let mut part = match value.strip_prefix('@') {
+        Some(path) => MultipartPart::file(name, path),
+        None => MultipartPart::text(name, value),
+    };
+    for field in fields {
+        match field.split_once('=') {
+            Some(("type", v)) => part.content_type = Some(v.to_string()),
+            Some(("filename", v)) => part.filename = Some(v.to_string()),
+            _ => {}
+        }
+    }
+    Ok(part)
+}
+
+/// `content`, `=content`, `name=content`, `@file` or `name@file` of `curl --data-urlencode`
+fn urlencode_data(data: &str) -> Result<String> {
+    let encode = |s: &str| form_urlencoded::byte_serialize(s.as_bytes()).collect::<String>();
+    let read = |path: &str| {
+        std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read curl data file: {}", path))
+    };
+
+    if let Some((name, content)) = data.split_once('=') {
+        return Ok(match name {
+            "" => encode(content),
+            name => format!("{}={}", name, encode(content)),
+        });
+    }
+    match data.split_once('@') {
+        Some(("", path)) => Ok(encode(&read(path)?)),
+        Some((name, path)) => Ok(format!("{}={}", name, encode(&read(path)?))),
+        None => Ok(encode(data)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(command: &str) -> Vec<String> {
+        shell_words::split(command).unwrap()
+    }
+
+    #[test]
+    fn curl_as_an_option_value_does_not_start_a_command() {
+        let profiles = parse_curl_args(&args(
+            "curl -A curl -d curl https://a.test/x curl https://b.test/y",
+        ))
+        .unwrap();
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(profiles[0].url.as_str(), "https://a.test/x");
+        assert_eq!(profiles[0].user_agent.as_deref(), Some("curl"));
+        assert_eq!(profiles[0].method, Method::POST);
+        assert_eq!(profiles[1].url.as_str(), "https://b.test/y");
+    }
+
+    #[test]
+    fn ignored_options_keep_their_value_away_from_the_url() {
+        let profile = RequestProfile::from_curl(
+            "curl --limit-rate 100K --connect-timeout 5 -o out.json https://a.test/x",
+        )
+        .unwrap();
+        assert_eq!(profile.url.as_str(), "https://a.test/x");
+    }
+
+    #[test]
+    fn unknown_options_are_rejected() {
+        let err =
+            RequestProfile::from_curl("curl --upload-file a.txt https://a.test/x").unwrap_err();
+        assert!(err.to_string().contains("--upload-file"));
+    }
+
+    #[test]
+    fn attached_and_bundled_short_options() {
+        let profile =
+            RequestProfile::from_curl("curl -sSLk -XPUT -HX-Id:1 https://a.test/x").unwrap();
+        assert_eq!(profile.method, Method::PUT);
+        assert_eq!(profile.tls.insecure, Some(true));
+        assert_eq!(profile.headers.get("x-id").unwrap(), "1");
+
+        let profile = RequestProfile::from_curl("curl -sXDELETE https://a.test/x").unwrap();
+        assert_eq!(profile.method, Method::DELETE);
+    }
+
+    #[test]
+    fn long_options_with_equals() {
+        let profile =
+            RequestProfile::from_curl("curl --request=PATCH --url=https://a.test/x").unwrap();
+        assert_eq!(profile.method, Method::PATCH);
+        assert_eq!(profile.url.as_str(), "https://a.test/x");
+        assert!(RequestProfile::from_curl("curl --compressed=1 https://a.test/x").is_err());
+    }
+
+    #[test]
+    fn a_single_command_rejects_a_second_one() {
+        assert!(RequestProfile::from_curl("curl https://a.test/x curl https://b.test/y").is_err());
+    }
+
+    #[tokio::test]
+    async fn query_values_stay_strings() {
+        let profile = RequestProfile::from_curl(
+            "curl 'https://a.test/x?v=1.10&e=1e3&id=12345678901234567890123&n=null'",
+        )
+        .unwrap();
+        assert_eq!(
+            profile.params,
+            json!({ "v": "1.10", "e": "1e3", "id": "12345678901234567890123", "n": "null" })
+        );
+        let req = profile
+            .prepare_request(&crate::ExtraArgs::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            req.url().query(),
+            Some("e=1e3&id=12345678901234567890123&n=null&v=1.10")
+        );
+
+        let profile = RequestProfile::from_curl("curl -G -d v=1.10 https://a.test/x").unwrap();
+        assert_eq!(profile.params, json!({ "v": "1.10" }));
+    }
+}
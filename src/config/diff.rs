@@ -1,6 +1,11 @@
 use std::collections::HashMap;
+use std::fmt::Write;
 
-use super::{is_default, LoadConfig, ValidateConfig};
+use super::markup::{parse_selector, validate_xpath};
+use super::{
+    diff_binary, is_default, BodyFormat, Expect, LoadConfig, RequestDefaults, ResponseReport,
+    Timing, ValidateConfig,
+};
 use anyhow::{Context, Ok, Result};
 use serde::{Deserialize, Serialize};
 
@@ -10,9 +15,18 @@ use crate::{diff_text_to_terminal_inline, ExtraArgs, RequestProfile};
 pub struct DiffConfig {
     #[serde(flatten)]
     pub profiles: HashMap<String, DiffProfile>,
+    #[serde(skip_serializing_if = "is_default", default)]
+    pub defaults: RequestDefaults,
 }
 
-impl LoadConfig for DiffConfig {}
+impl LoadConfig for DiffConfig {
+    fn apply_defaults(&mut self) {
+        for profile in self.profiles.values_mut() {
+            profile.req1.apply_defaults(&self.defaults);
+            profile.req2.apply_defaults(&self.defaults);
+        }
+    }
+}
 impl ValidateConfig for DiffConfig {
     fn validate(&self) -> Result<()> {
         for (name, propfile) in &self.profiles {
@@ -24,9 +38,12 @@ impl ValidateConfig for DiffConfig {
 
 impl DiffConfig {
     pub fn new(profiles: HashMap<String, DiffProfile>) -> Self {
-        Self { profiles }
+        Self {
+            profiles,
+            defaults: RequestDefaults::default(),
+        }
     }
-    
+
     pub fn get_profile(&self, name: &str) -> Option<&DiffProfile> {
         self.profiles.get(name)
     }
@@ -38,33 +55,209 @@ pub struct DiffProfile {
     pub req2: RequestProfile,
     #[serde(skip_serializing_if = "is_default", default)]
     pub res: ResponseProfile,
+    /// checked on both responses, on top of the `expect` of each request, `expect.schema`
+    /// validates both bodies
+    #[serde(skip_serializing_if = "is_default", default)]
+    pub expect: Expect,
+}
+
+/// the diff of two responses and the expectations they failed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiffResult {
+    pub output: String,
+    /// `req1: status 500 is not 2xx`
+    pub failures: Vec<String>,
 }
 
 impl DiffProfile {
     pub fn new(req1: RequestProfile, req2: RequestProfile, res: ResponseProfile) -> Self {
-        Self { req1, req2, res }
+        Self {
+            req1,
+            req2,
+            res,
+            expect: Expect::default(),
+        }
     }
-    pub async fn diff(&self, args: ExtraArgs) -> Result<String> {
+    pub async fn diff(&self, args: ExtraArgs) -> Result<DiffResult> {
+        if self.req1.websocket.is_some() {
+            let text1 = self.req1.converse(&args, &self.res.skip_body).await?;
+            let text2 = self.req2.converse(&args, &self.res.skip_body).await?;
+            return Ok(DiffResult {
+                output: diff_text_to_terminal_inline(&text1, &text2)?,
+                failures: vec![],
+            });
+        }
+        if self.req1.grpc.is_some() {
+            let text1 = self.req1.invoke(&args, &self.res).await?;
+            let text2 = self.req2.invoke(&args, &self.res).await?;
+            return Ok(DiffResult {
+                output: diff_text_to_terminal_inline(&text1, &text2)?,
+                failures: vec![],
+            });
+        }
         let res1 = self.req1.send(&args).await?;
         let res2 = self.req2.send(&args).await?;
-        let text1 = res1.filter_text(&self.res).await?;
-        let text2 = res2.filter_text(&self.res).await?;
-        diff_text_to_terminal_inline(&text1, &text2)
+        let mut output = String::new();
+        for (name, res) in [("req1", &res1), ("req2", &res2)] {
+            if res.attempts() > 1 {
+                writeln!(
+                    output,
+                    "{} succeeded after {} attempts",
+                    name,
+                    res.attempts()
+                )?;
+            }
+        }
+        let expects1 = [&self.expect, &self.req1.expect];
+        let expects2 = [&self.expect, &self.req2.expect];
+        let mut failures = vec![];
+        if self.is_graphql() {
+            let (head1, gql1, report1) = res1.filter_graphql(&self.res, &expects1).await?;
+            let (head2, gql2, report2) = res2.filter_graphql(&self.res, &expects2).await?;
+            let timing = (report1.timing, report2.timing);
+            add_failures(&mut failures, report1, report2);
+            for (name, text1, text2) in [
+                ("response", &head1, &head2),
+                ("data", &gql1.data, &gql2.data),
+                ("errors", &gql1.errors, &gql2.errors),
+            ] {
+                let diff = diff_text_to_terminal_inline(text1, text2)?;
+                if !diff.is_empty() {
+                    writeln!(output, "======== {} ========", name)?;
+                    writeln!(output, "{}", diff)?;
+                }
+            }
+            self.add_timing(&mut output, &mut failures, timing)?;
+            return Ok(DiffResult { output, failures });
+        }
+        let (text1, bytes1, report1) = res1.filter_text(&self.res, &expects1).await?;
+        let (text2, bytes2, report2) = res2.filter_text(&self.res, &expects2).await?;
+        let timing = (report1.timing, report2.timing);
+        add_failures(&mut failures, report1, report2);
+        write!(output, "{}", diff_text_to_terminal_inline(&text1, &text2)?)?;
+        if let (Some(bytes1), Some(bytes2)) = (bytes1, bytes2) {
+            if bytes1 != bytes2 {
+                let (hex1, hex2) = diff_binary(&bytes1, &bytes2);
+                if !output.is_empty() && !output.ends_with('\n') {
+                    writeln!(output)?;
+                }
+                writeln!(output, "======== binary ========")?;
+                write!(output, "{}", diff_text_to_terminal_inline(&hex1, &hex2)?)?;
+            }
+        }
+        self.add_timing(&mut output, &mut failures, timing)?;
+        Ok(DiffResult { output, failures })
+    }
+
+    /// latency and size of both responses after the diff when `timing` or `max_latency_ratio`
+    /// is set, a ttfb regression over `max_latency_ratio` is a failure
+    fn add_timing(
+        &self,
+        output: &mut String,
+        failures: &mut Vec<String>,
+        (timing1, timing2): (Timing, Timing),
+    ) -> Result<()> {
+        if !self.res.timing && self.res.max_latency_ratio.is_none() {
+            return Ok(());
+        }
+        if !output.is_empty() && !output.ends_with('\n') {
+            writeln!(output)?;
+        }
+        writeln!(output, "======== timing ========")?;
+        write!(output, "{}", timing1.compare(&timing2)?)?;
+        if let Some(max) = self.res.max_latency_ratio {
+            let (ttfb1, ttfb2) = (timing1.ttfb.as_secs_f64(), timing2.ttfb.as_secs_f64());
+            if ttfb2 > ttfb1 * max {
+                failures.push(format!(
+                    "req2 ttfb {}ms is over {} times req1 {}ms",
+                    timing2.ttfb.as_millis(),
+                    max,
+                    timing1.ttfb.as_millis()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// graphql responses are diffed as separate `data` and `errors` sections
+    pub fn is_graphql(&self) -> bool {
+        self.res.graphql || (self.req1.graphql.is_some() && self.req2.graphql.is_some())
     }
 
     pub(crate) fn validate(&self) -> Result<()> {
         self.req1.validate().context("req1 config is failed")?;
         self.req2.validate().context("req2 config is failed")?;
+        self.expect.validate().context("expect config is failed")?;
+        self.res.validate().context("res config is failed")?;
+        if self.req1.websocket.is_some() != self.req2.websocket.is_some() {
+            return Err(anyhow::anyhow!("req1 和 req2 必须都是 websocket 或都不是"));
+        }
+        if self.req1.websocket.is_some() && !is_default(&self.expect) {
+            return Err(anyhow::anyhow!("websocket 不支持 expect"));
+        }
+        // only the messages are diffed, there is no response to time or normalize
+        let messages_only = ResponseProfile::new(vec![], self.res.skip_body.clone());
+        if self.req1.websocket.is_some() && self.res != messages_only {
+            return Err(anyhow::anyhow!("websocket 的 res 只支持 skip_body"));
+        }
+        if self.req1.grpc.is_some() != self.req2.grpc.is_some() {
+            return Err(anyhow::anyhow!("req1 和 req2 必须都是 grpc 或都不是"));
+        }
+        if self.req1.grpc.is_some() && !is_default(&self.expect) {
+            return Err(anyhow::anyhow!("grpc 不支持 expect"));
+        }
+        let metadata_and_message =
+            ResponseProfile::new(self.res.skip_headers.clone(), self.res.skip_body.clone());
+        if self.req1.grpc.is_some() && self.res != metadata_and_message {
+            return Err(anyhow::anyhow!(
+                "grpc 的 res 只支持 skip_headers 和 skip_body"
+            ));
+        }
         Ok(())
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+/// the failed expectations of both responses, prefixed with the side
+fn add_failures(failures: &mut Vec<String>, report1: ResponseReport, report2: ResponseReport) {
+    for (name, report) in [("req1", report1), ("req2", report2)] {
+        failures.extend(
+            report
+                .failures
+                .into_iter()
+                .map(|f| format!("{}: {}", name, f)),
+        );
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
 pub struct ResponseProfile {
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub skip_headers: Vec<String>,
+    /// top level keys left out of json and yaml bodies
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub skip_body: Vec<String>,
+    /// xpaths of the nodes and attributes left out of xml bodies, e.g. `//item/@updated`
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub skip_xpath: Vec<String>,
+    /// css selectors of the elements left out of html bodies
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub skip_css: Vec<String>,
+    /// normalize bodies as this format whatever their content type says
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub body_format: Option<BodyFormat>,
+    /// bytes of each body that are read, the rest is not downloaded
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_body_size: Option<u64>,
+    /// diff graphql `data` and `errors` separately, on by default when both requests are graphql
+    #[serde(skip_serializing_if = "is_default", default)]
+    pub graphql: bool,
+    /// show the latency and size of both responses after the diff
+    #[serde(skip_serializing_if = "is_default", default)]
+    pub timing: bool,
+    /// fail when the ttfb of req2 is more than this many times that of req1, e.g. `1.5`,
+    /// the timing is shown as well
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_latency_ratio: Option<f64>,
 }
 
 impl ResponseProfile {
@@ -72,6 +265,29 @@ impl ResponseProfile {
         Self {
             skip_headers,
             skip_body,
+            skip_xpath: vec![],
+            skip_css: vec![],
+            body_format: None,
+            max_body_size: None,
+            graphql: false,
+            timing: false,
+            max_latency_ratio: None,
         }
     }
+
+    fn validate(&self) -> Result<()> {
+        for path in &self.skip_xpath {
+            validate_xpath(path)?;
+        }
+        for selector in &self.skip_css {
+            parse_selector(selector)?;
+        }
+        if self
+            .max_latency_ratio
+            .is_some_and(|ratio| ratio.is_nan() || ratio <= 0.0)
+        {
+            return Err(anyhow::anyhow!("max_latency_ratio 必须大于 0"));
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,351 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use http::{HeaderName, StatusCode};
+use mime::Mime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{get_content_type, schema::JsonSchema, ResponseExt};
+
+/// checks a response has to pass, reported apart from the diff
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Expect {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub status: Option<StatusExpect>,
+    /// `name` has to be present, `name: value` has to match exactly
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub headers: Vec<String>,
+    /// compared without parameters, e.g. `application/json`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub content_type: Option<String>,
+    /// milliseconds until the response headers arrive
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_latency: Option<u64>,
+    /// path of a json schema the body has to match
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub schema: Option<String>,
+}
+
+/// `200`, `2xx`, `200-299` or a list of them
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(try_from = "Value", into = "Value")]
+pub struct StatusExpect(pub Vec<StatusRange>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatusRange {
+    pub min: u16,
+    pub max: u16,
+}
+
+impl FromStr for StatusRange {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_lowercase();
+        let invalid = || anyhow!("expect.status: {} 不是有效的状态码", s);
+        if let Some(class) = s.strip_suffix("xx") {
+            let class: u16 = class.parse().map_err(|_| invalid())?;
+            if !(1..=5).contains(&class) {
+                return Err(invalid());
+            }
+            return Ok(Self {
+                min: class * 100,
+                max: class * 100 + 99,
+            });
+        }
+        let (min, max) = match s.split_once('-') {
+            Some((min, max)) => (min.trim().parse(), max.trim().parse()),
+            None => (s.parse(), s.parse()),
+        };
+        match (min, max) {
+            (Ok(min), Ok(max)) if min <= max && (100..=599).contains(&min) => Ok(Self { min, max }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for StatusRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.min == self.max {
+            write!(f, "{}", self.min)
+        } else if self.min.is_multiple_of(100) && self.max == self.min + 99 {
+            write!(f, "{}xx", self.min / 100)
+        } else {
+            write!(f, "{}-{}", self.min, self.max)
+        }
+    }
+}
+
+impl StatusRange {
+    fn matches(&self, status: StatusCode) -> bool {
+        (self.min..=self.max).contains(&status.as_u16())
+    }
+
+    fn to_value(self) -> Value {
+        match self.min == self.max {
+            true => Value::from(self.min),
+            false => Value::String(self.to_string()),
+        }
+    }
+}
+
+impl TryFrom<Value> for StatusExpect {
+    type Error = anyhow::Error;
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let one = |v: &Value| match v {
+            Value::Number(n) => n.to_string().parse(),
+            Value::String(s) => s.parse(),
+            v => Err(anyhow!("expect.status: {} 不是有效的状态码", v)),
+        };
+        match &value {
+            Value::Array(values) => Ok(Self(values.iter().map(one).collect::<Result<_>>()?)),
+            v => Ok(Self(vec![one(v)?])),
+        }
+    }
+}
+
+impl From<StatusExpect> for Value {
+    fn from(value: StatusExpect) -> Self {
+        match value.0.as_slice() {
+            [one] => one.to_value(),
+            ranges => Value::Array(ranges.iter().map(|r| r.to_value()).collect()),
+        }
+    }
+}
+
+impl fmt::Display for StatusExpect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ranges: Vec<String> = self.0.iter().map(|r| r.to_string()).collect();
+        write!(f, "{}", ranges.join(" or "))
+    }
+}
+
+impl Expect {
+    pub(crate) fn validate(&self) -> Result<()> {
+        for header in &self.headers {
+            let name = header.split_once(':').map_or(header.as_str(), |(k, _)| k);
+            HeaderName::from_bytes(name.trim().as_bytes())
+                .with_context(|| format!("expect.headers: {} 不是有效的 header", header))?;
+        }
+        if let Some(content_type) = &self.content_type {
+            content_type.parse::<Mime>().with_context(|| {
+                format!(
+                    "expect.content_type: {} 不是有效的 content type",
+                    content_type
+                )
+            })?;
+        }
+        if let Some(path) = &self.schema {
+            JsonSchema::cached(path)?;
+        }
+        Ok(())
+    }
+
+    /// what the body doesn't meet, only the schema looks at the body, a body cut off by
+    /// `max_body_size` can't be checked
+    pub fn check_body(&self, text: &str, truncated: bool) -> Result<Vec<String>> {
+        let Some(path) = &self.schema else {
+            return Ok(vec![]);
+        };
+        if truncated {
+            return Ok(vec![
+                "schema not checked, the body was truncated by max_body_size".to_string(),
+            ]);
+        }
+        let schema = JsonSchema::cached(path)?;
+        Ok(schema
+            .validate_text(text)
+            .iter()
+            .map(|e| format!("schema {}", e))
+            .collect())
+    }
+}
+
+impl ResponseExt {
+    /// what the status, headers and latency of the response don't meet
+    pub fn check(&self, expect: &Expect) -> Vec<String> {
+        let mut failures = vec![];
+        let res = &self.res;
+        if let Some(status) = &expect.status {
+            if !status.0.iter().any(|r| r.matches(res.status())) {
+                failures.push(format!(
+                    "status {} is not {}",
+                    res.status().as_u16(),
+                    status
+                ));
+            }
+        }
+        for header in &expect.headers {
+            let (name, value) = match header.split_once(':') {
+                Some((k, v)) => (k.trim(), Some(v.trim())),
+                None => (header.trim(), None),
+            };
+            let actual = res
+                .headers()
+                .get(name)
+                .map(|v| String::from_utf8_lossy(v.as_bytes()));
+            match (actual, value) {
+                (None, _) => failures.push(format!("header {} is missing", name)),
+                (Some(actual), Some(value)) if actual != value => failures.push(format!(
+                    "header {} is {:?}, expected {:?}",
+                    name, actual, value
+                )),
+                _ => {}
+            }
+        }
+        if let Some(expected) = &expect.content_type {
+            let actual = get_content_type(res.headers());
+            let matches = match (&actual, expected.parse::<Mime>()) {
+                (Some(actual), Ok(expected)) => actual.essence_str() == expected.essence_str(),
+                _ => false,
+            };
+            if !matches {
+                let actual = actual.map_or("none".to_string(), |m| m.to_string());
+                failures.push(format!("content type {} is not {}", actual, expected));
+            }
+        }
+        if let Some(max) = expect.max_latency {
+            let elapsed = self.elapsed().as_millis();
+            if elapsed > max as u128 {
+                failures.push(format!("latency {}ms is over {}ms", elapsed, max));
+            }
+        }
+        failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::config::Timing;
+
+    fn response(status: u16, headers: &[(&str, &str)], ttfb: u64) -> ResponseExt {
+        let mut res = http::Response::builder().status(status);
+        for (k, v) in headers {
+            res = res.header(*k, *v);
+        }
+        ResponseExt {
+            res: res.body("").unwrap().into(),
+            attempts: 1,
+            redirects: vec![],
+            timing: Timing {
+                ttfb: Duration::from_millis(ttfb),
+                ..Timing::default()
+            },
+            sse: None,
+            protobuf: None,
+        }
+    }
+
+    fn expect(yaml: &str) -> Expect {
+        let expect: Expect = serde_yaml::from_str(yaml).unwrap();
+        expect.validate().unwrap();
+        expect
+    }
+
+    #[test]
+    fn status_ranges() {
+        let range = |s: &str| s.parse::<StatusRange>().map(|r| (r.min, r.max));
+        assert_eq!(range("201").unwrap(), (201, 201));
+        assert_eq!(range("2XX").unwrap(), (200, 299));
+        assert_eq!(range("200 - 204").unwrap(), (200, 204));
+        for invalid in ["6xx", "0xx", "204-200", "99", "abc", "600"] {
+            assert!(range(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn status_expectations_round_trip() {
+        let status: StatusExpect = serde_yaml::from_str("[200, 3xx, 400-404]").unwrap();
+        assert_eq!(status.to_string(), "200 or 3xx or 400-404");
+        assert_eq!(
+            serde_yaml::to_string(&status).unwrap(),
+            "- 200\n- 3xx\n- 400-404\n"
+        );
+        let status: StatusExpect = serde_yaml::from_str("2xx").unwrap();
+        assert_eq!(serde_yaml::to_string(&status).unwrap(), "2xx\n");
+        assert!(serde_yaml::from_str::<StatusExpect>("[200, true]").is_err());
+    }
+
+    #[test]
+    fn invalid_headers_and_content_types_are_rejected() {
+        let parse = |yaml: &str| serde_yaml::from_str::<Expect>(yaml).unwrap().validate();
+        assert!(parse("headers: ['bad header: 1']").is_err());
+        assert!(parse("content_type: json").is_err());
+        assert!(
+            parse("{headers: ['x-id', 'x-env: prod'], content_type: application/json}").is_ok()
+        );
+    }
+
+    #[test]
+    fn a_response_meeting_everything_has_no_failures() {
+        let expect = expect(
+            "{status: [2xx, 304], headers: [x-id, 'x-env: prod'], content_type: application/json, max_latency: 100}",
+        );
+        let res = response(
+            204,
+            &[
+                ("x-id", "1"),
+                ("x-env", "prod"),
+                ("content-type", "application/json; charset=utf-8"),
+            ],
+            50,
+        );
+        assert!(res.check(&expect).is_empty());
+    }
+
+    #[test]
+    fn every_unmet_expectation_is_reported() {
+        let expect = expect(
+            "{status: 2xx, headers: [x-id, 'x-env: prod'], content_type: application/json, max_latency: 100}",
+        );
+        let res = response(500, &[("x-env", "dev"), ("content-type", "text/html")], 150);
+        assert_eq!(
+            res.check(&expect),
+            [
+                "status 500 is not 2xx",
+                "header x-id is missing",
+                "header x-env is \"dev\", expected \"prod\"",
+                "content type text/html is not application/json",
+                "latency 150ms is over 100ms",
+            ]
+        );
+        let res = response(200, &[], 0);
+        assert!(res.check(&expect)[1..]
+            .iter()
+            .any(|f| f == "content type none is not application/json"));
+    }
+
+    #[test]
+    fn the_body_is_only_checked_against_a_schema() {
+        assert!(Expect::default()
+            .check_body("not json", false)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn a_truncated_body_is_reported_as_truncated() {
+        let path = std::env::temp_dir().join(format!("netdiff-expect-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"type": "object"}"#).unwrap();
+        let expect = Expect {
+            schema: Some(path.to_string_lossy().into_owned()),
+            ..Expect::default()
+        };
+        let truncated = expect.check_body(r#"{"a": "#, true).unwrap();
+        let complete = expect.check_body(r#"{"a": "#, false).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            truncated,
+            ["schema not checked, the body was truncated by max_body_size"]
+        );
+        assert!(
+            complete[0].starts_with("schema /: body is not json"),
+            "{:?}",
+            complete
+        );
+    }
+}
@@ -0,0 +1,393 @@
+use std::fmt::Write;
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::{
+    header::{ACCEPT, ACCEPT_ENCODING, USER_AGENT},
+    Method,
+};
+use reqwest::Request;
+use shell_words::quote;
+use url::Url;
+
+use super::{redirect::DEFAULT_MAX_REDIRECTS, FollowRedirects, RequestProfile};
+use crate::ExtraArgs;
+
+/// the encodings reqwest asks for with the gzip, brotli and deflate features
+const ACCEPT_ENCODING_VALUE: &str = "gzip, br, deflate";
+
+impl RequestProfile {
+    /// render the request as a curl command
+    pub async fn to_curl(&self, extra_args: &ExtraArgs) -> Result<String> {
+        self.check_exportable()?;
+        let req = self.prepare_request(extra_args).await?;
+        let mut args: Vec<String> = vec!["curl".into()];
+        let body = body_arg(&req);
+
+        // curl sends GET, or POST along with data, unless told otherwise
+        match (req.method(), &body) {
+            (&Method::GET, None) | (&Method::POST, Some(_)) => {}
+            (&Method::HEAD, None) => args.push("--head".into()),
+            (method, _) => args.extend(["-X".into(), method.to_string()]),
+        }
+        args.push(quote(self.display_url(&req)?.as_str()).into_owned());
+        for (k, v) in req.headers() {
+            let header = format!("{}: {}", k, String::from_utf8_lossy(v.as_bytes()));
+            args.extend(["-H".into(), quote(&header).into_owned()]);
+        }
+        // reqwest sends no user agent, curl would send its own
+        if !req.headers().contains_key(USER_AGENT) {
+            args.extend(["-H".into(), quote("User-Agent:").into_owned()]);
+        }
+        if !req.headers().contains_key(ACCEPT_ENCODING) {
+            args.push("--compressed".into());
+        }
+        if let Some((flag, body)) = body {
+            args.extend([flag.into(), body]);
+        }
+
+        match self.follow_redirects {
+            Some(FollowRedirects::None) => {}
+            Some(FollowRedirects::Limit(n)) => {
+                args.extend(["-L".into(), "--max-redirs".into(), n.to_string()])
+            }
+            None => args.extend([
+                "-L".into(),
+                "--max-redirs".into(),
+                DEFAULT_MAX_REDIRECTS.to_string(),
+            ]),
+        }
+        if let Some(timeout) = self.retry.timeout {
+            args.extend(["--max-time".into(), format!("{}", timeout as f64 / 1000.0)]);
+        }
+        if let Some(retries) = self.retry.retries {
+            args.extend(["--retry".into(), retries.to_string()]);
+        }
+        if let Some(proxy) = &self.route.proxy {
+            args.extend(["-x".into(), quote(proxy).into_owned()]);
+        }
+        if let Some(no_proxy) = &self.route.no_proxy {
+            args.extend(["--noproxy".into(), quote(no_proxy).into_owned()]);
+        }
+        for entry in &self.route.resolve {
+            args.extend(["--resolve".into(), quote(entry).into_owned()]);
+        }
+        if let Some(server_name) = &self.tls.server_name {
+            let host = self.url.host_str().unwrap_or_default();
+            let port = self.url.port_or_known_default().unwrap_or(443);
+            // the url points at server_name, the connection still goes to the original host
+            let connect_to = format!("{}:{}:{}:{}", server_name, port, host, port);
+            args.extend(["--connect-to".into(), quote(&connect_to).into_owned()]);
+        }
+        if self.tls.insecure.unwrap_or(false) {
+            args.push("-k".into());
+        }
+        if let Some(ca) = &self.tls.ca_cert {
+            args.extend(["--cacert".into(), quote(ca).into_owned()]);
+        }
+        if let Some(cert) = &self.tls.client_cert {
+            args.extend(["--cert".into(), quote(cert).into_owned()]);
+        }
+        if let Some(key) = &self.tls.client_key {
+            args.extend(["--key".into(), quote(key).into_owned()]);
+        }
+
+        Ok(join_lines(args, |arg| arg.starts_with('-')))
+    }
+
+    /// render the request as an httpie command
+    pub async fn to_httpie(&self, extra_args: &ExtraArgs) -> Result<String> {
+        self.check_exportable()?;
+        let req = self.prepare_request(extra_args).await?;
+        let mut args: Vec<String> = vec![];
+
+        let stdin = req
+            .body()
+            .and_then(|b| b.as_bytes())
+            .filter(|b| std::str::from_utf8(b).is_err());
+        let mut prefix = String::new();
+        match stdin {
+            // httpie can't take binary data as an argument, pipe it in
+            Some(bytes) => write!(
+                prefix,
+                "echo {} | base64 -d | ",
+                quote(&STANDARD.encode(bytes))
+            )?,
+            None => args.push("--ignore-stdin".into()),
+        }
+        args.insert(0, "http".into());
+
+        match self.follow_redirects {
+            Some(FollowRedirects::None) => {}
+            Some(FollowRedirects::Limit(n)) => {
+                args.extend(["--follow".into(), format!("--max-redirects={}", n)])
+            }
+            None => args.extend([
+                "--follow".into(),
+                format!("--max-redirects={}", DEFAULT_MAX_REDIRECTS),
+            ]),
+        }
+        if let Some(timeout) = self.retry.timeout {
+            args.push(format!("--timeout={}", timeout as f64 / 1000.0));
+        }
+        if let Some(proxy) = &self.route.proxy {
+            for scheme in ["http", "https"] {
+                args.push(quote(&format!("--proxy={}:{}", scheme, proxy)).into_owned());
+            }
+        }
+        if self.tls.insecure.unwrap_or(false) {
+            args.push("--verify=no".into());
+        } else if let Some(ca) = &self.tls.ca_cert {
+            args.push(quote(&format!("--verify={}", ca)).into_owned());
+        }
+        if let Some(cert) = &self.tls.client_cert {
+            args.push(quote(&format!("--cert={}", cert)).into_owned());
+        }
+        if let Some(key) = &self.tls.client_key {
+            args.push(quote(&format!("--cert-key={}", key)).into_owned());
+        }
+
+        args.push(req.method().to_string());
+        args.push(quote(self.display_url(&req)?.as_str()).into_owned());
+        for (k, v) in req.headers() {
+            let header = format!("{}:{}", k, String::from_utf8_lossy(v.as_bytes()));
+            args.push(quote(&header).into_owned());
+        }
+        // reqwest sends no user agent, httpie would send its own
+        if !req.headers().contains_key(USER_AGENT) {
+            args.push(quote("User-Agent:").into_owned());
+        }
+        if let Some(body) = req.body().and_then(|b| b.as_bytes()) {
+            if let (Ok(text), None) = (std::str::from_utf8(body), stdin) {
+                if !text.is_empty() {
+                    args.push(quote(&format!("--raw={}", text)).into_owned());
+                }
+            }
+        }
+
+        let mut output = prefix;
+        output.push_str(&join_lines(args, |_| true));
+        if !self.route.resolve.is_empty() {
+            write!(
+                output,
+                "\n# httpie has no --resolve, route these hosts yourself: {}",
+                self.route.resolve.join(" ")
+            )?;
+        }
+        Ok(output)
+    }
+
+    /// render the request as an HTTP/1.1 message
+    pub async fn to_raw_http(&self, extra_args: &ExtraArgs) -> Result<Vec<u8>> {
+        self.check_exportable()?;
+        let req = self.prepare_request(extra_args).await?;
+        let url = req.url();
+        let body = req.body().and_then(|b| b.as_bytes()).unwrap_or_default();
+
+        let mut head = String::new();
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        write!(head, "{} {} HTTP/1.1\r\n", req.method(), path)?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            _ => String::new(),
+        };
+        let host = match &self.tls.server_name {
+            Some(server_name) => server_name.clone(),
+            None => host,
+        };
+        write!(head, "host: {}\r\n", host)?;
+        for (k, v) in req.headers() {
+            write!(head, "{}: {}\r\n", k, String::from_utf8_lossy(v.as_bytes()))?;
+        }
+        if !req.headers().contains_key(ACCEPT) {
+            write!(head, "accept: */*\r\n")?;
+        }
+        if !req.headers().contains_key(ACCEPT_ENCODING) {
+            write!(head, "accept-encoding: {}\r\n", ACCEPT_ENCODING_VALUE)?;
+        }
+        if !body.is_empty() {
+            write!(head, "content-length: {}\r\n", body.len())?;
+        }
+        head.push_str("\r\n");
+
+        let mut output = head.into_bytes();
+        output.extend_from_slice(body);
+        Ok(output)
+    }
+
+    /// websocket and grpc profiles aren't plain http requests
+    fn check_exportable(&self) -> Result<()> {
+        if self.websocket.is_some() {
+            return Err(anyhow!("websocket profiles can't be exported"));
+        }
+        if self.grpc.is_some() {
+            return Err(anyhow!("grpc profiles can't be exported"));
+        }
+        Ok(())
+    }
+
+    /// the url of the request, with the host replaced by `tls.server_name` if set
+    fn display_url(&self, req: &Request) -> Result<Url> {
+        let mut url = req.url().clone();
+        if let Some(server_name) = &self.tls.server_name {
+            url.set_host(Some(server_name))?;
+        }
+        Ok(url)
+    }
+}
+
+/// the curl flag and shell argument of the body, binary data is decoded from base64 by the shell,
+/// `--data-raw` keeps a leading `@` from being read as a file name
+fn body_arg(req: &Request) -> Option<(&'static str, String)> {
+    let body = req.body()?.as_bytes()?;
+    if body.is_empty() {
+        return None;
+    }
+    Some(match std::str::from_utf8(body) {
+        Ok(text) => ("--data-raw", quote(text).into_owned()),
+        Err(_) => (
+            "--data-binary",
+            format!("@<(echo {} | base64 -d)", STANDARD.encode(body)),
+        ),
+    })
+}
+
+/// join the args with line continuations before every arg matching `break_before`
+fn join_lines(args: Vec<String>, break_before: impl Fn(&str) -> bool) -> String {
+    let mut output = String::new();
+    let mut iter = args.into_iter().peekable();
+    while let Some(arg) = iter.next() {
+        output.push_str(&arg);
+        match iter.peek() {
+            Some(next) if break_before(next) => output.push_str(" \\\n  "),
+            Some(_) => output.push(' '),
+            None => {}
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(yaml: &str) -> RequestProfile {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    async fn curl(yaml: &str) -> String {
+        profile(yaml).to_curl(&ExtraArgs::default()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn get_without_a_body_has_no_data_or_method() {
+        let output = curl("url: https://a.test/x?a=1").await;
+        assert_eq!(
+            output,
+            "curl 'https://a.test/x?a=1' \\\n  -H User-Agent: \\\n  --compressed \\\n  -L \\\n  --max-redirs 10"
+        );
+    }
+
+    #[tokio::test]
+    async fn the_method_is_passed_unless_curl_infers_it() {
+        let output = curl("{method: POST, url: 'https://a.test/x', body: {a: 1}}").await;
+        assert!(!output.contains("-X"), "{}", output);
+        assert!(output.contains(r#"--data-raw '{"a":1}'"#), "{}", output);
+
+        let output = curl("{method: PUT, url: 'https://a.test/x', body: {a: 1}}").await;
+        assert!(output.contains("-X PUT"), "{}", output);
+
+        let output = curl("{method: POST, url: 'https://a.test/x'}").await;
+        assert!(output.contains("-X POST"), "{}", output);
+        assert!(!output.contains("--data"), "{}", output);
+
+        let output = curl("{method: GET, url: 'https://a.test/x', body: {a: 1}}").await;
+        assert!(output.contains("-X GET"), "{}", output);
+
+        let output = curl("{method: DELETE, url: 'https://a.test/x'}").await;
+        assert!(output.contains("-X DELETE"), "{}", output);
+
+        let output = curl("{method: HEAD, url: 'https://a.test/x'}").await;
+        assert!(
+            output.contains("--head") && !output.contains("-X"),
+            "{}",
+            output
+        );
+    }
+
+    #[tokio::test]
+    async fn bodies_starting_with_at_are_sent_as_is() {
+        let output =
+            curl("{method: POST, url: 'https://a.test/x', body: '@/etc/passwd', body_type: text}")
+                .await;
+        assert!(output.contains("--data-raw @/etc/passwd"), "{}", output);
+        assert!(!output.contains("--data-binary"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn binary_bodies_are_piped_through_base64() {
+        let output =
+            curl("{method: POST, url: 'https://a.test/x', body: //79, body_type: base64}").await;
+        assert!(
+            output.contains("--data-binary @<(echo //79 | base64 -d)"),
+            "{}",
+            output
+        );
+    }
+
+    #[tokio::test]
+    async fn shell_metacharacters_are_quoted() {
+        let output = curl(
+            r#"{method: POST, url: 'https://a.test/x', headers: {x-note: "it's $HOME"}, body: "a b;`c`", body_type: text}"#,
+        )
+        .await;
+        let args = shell_words::split(&output.replace("\\\n", "")).unwrap();
+        let at = |flag: &str, value: &str| {
+            args.windows(2)
+                .any(|pair| pair[0] == flag && pair[1] == value)
+        };
+        assert!(at("-H", "x-note: it's $HOME"), "{}", output);
+        assert!(at("--data-raw", "a b;`c`"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn httpie_and_raw_http_skip_the_missing_body() {
+        let profile = profile("url: https://a.test/x");
+        let httpie = profile.to_httpie(&ExtraArgs::default()).await.unwrap();
+        let httpie = httpie.replace(" \\\n  ", " ");
+        assert!(!httpie.contains("--raw"), "{}", httpie);
+        assert!(httpie.contains("GET https://a.test/x"), "{}", httpie);
+
+        let raw = profile.to_raw_http(&ExtraArgs::default()).await.unwrap();
+        let raw = String::from_utf8(raw).unwrap();
+        assert!(
+            raw.starts_with("GET /x HTTP/1.1\r\nhost: a.test\r\n"),
+            "{}",
+            raw
+        );
+        assert!(
+            !raw.contains("content-length") && !raw.contains("content-type"),
+            "{}",
+            raw
+        );
+        assert!(raw.ends_with("\r\n\r\n"));
+    }
+
+    #[tokio::test]
+    async fn websocket_and_grpc_profiles_are_rejected() {
+        let ws = profile("{url: 'ws://a.test/', websocket: {}}");
+        assert!(ws.to_curl(&ExtraArgs::default()).await.is_err());
+        assert!(ws.to_httpie(&ExtraArgs::default()).await.is_err());
+        assert!(ws.to_raw_http(&ExtraArgs::default()).await.is_err());
+
+        let grpc = profile(
+            "{url: 'http://a.test/', grpc: {descriptor_set: a.pb, method: a.A/B, message: {}}}",
+        );
+        let err = grpc.to_curl(&ExtraArgs::default()).await.unwrap_err();
+        assert!(err.to_string().contains("grpc"));
+    }
+}
@@ -0,0 +1,151 @@
+use anyhow::Result;
+use mime::Mime;
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+
+use super::{
+    filter_html, filter_json, filter_ndjson, filter_sse, filter_xml, filter_yaml, ResponseProfile,
+};
+
+/// how a body is normalized before diffing
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyFormat {
+    Json,
+    /// newline delimited json, compared record by record
+    Ndjson,
+    Xml,
+    Html,
+    Yaml,
+    /// `text/event-stream`, compared event by event
+    Sse,
+    /// diffed as is
+    Text,
+}
+
+impl BodyFormat {
+    /// `application/json`, `text/json` and `+json` types, likewise for xml and yaml, the ndjson types and `text/event-stream`
+    pub fn from_mime(mime: &Mime) -> Option<Self> {
+        let format = match mime.suffix().unwrap_or_else(|| mime.subtype()).as_str() {
+            "json" => Self::Json,
+            "ndjson" | "x-ndjson" | "jsonl" | "jsonlines" | "x-jsonlines" => Self::Ndjson,
+            "xml" => Self::Xml,
+            "yaml" | "x-yaml" => Self::Yaml,
+            "html" => Self::Html,
+            "event-stream" => Self::Sse,
+            _ => return None,
+        };
+        match mime.type_().as_str() {
+            "application" | "text" => Some(format),
+            _ => None,
+        }
+    }
+
+    /// a guess from the body itself, for a missing or wrong content type
+    pub fn sniff(text: &str) -> Self {
+        let start = text.trim_start();
+        let head = start
+            .get(..start.len().min(14))
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if (start.starts_with('{') || start.starts_with('['))
+            && serde_json::from_str::<IgnoredAny>(text).is_ok()
+        {
+            Self::Json
+        } else if start.starts_with('{') && is_ndjson(start) {
+            Self::Ndjson
+        } else if head.starts_with("<!doctype html") || head.starts_with("<html") {
+            Self::Html
+        } else if start.starts_with('<') && roxmltree::Document::parse(text).is_ok() {
+            Self::Xml
+        } else {
+            Self::Text
+        }
+    }
+
+    pub fn filter(self, text: &str, profile: &ResponseProfile) -> Result<String> {
+        match self {
+            Self::Json => filter_json(text, &profile.skip_body),
+            Self::Ndjson => filter_ndjson(text, &profile.skip_body),
+            Self::Xml => filter_xml(text, &profile.skip_xpath),
+            Self::Html => filter_html(text, &profile.skip_css),
+            Self::Yaml => filter_yaml(text, &profile.skip_body),
+            Self::Sse => filter_sse(text, &profile.skip_body),
+            Self::Text => Ok(text.to_string()),
+        }
+    }
+}
+
+/// more than one line and every line a json value
+fn is_ndjson(text: &str) -> bool {
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    lines.len() > 1
+        && lines
+            .iter()
+            .all(|line| serde_json::from_str::<IgnoredAny>(line).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(mime: &str) -> Option<BodyFormat> {
+        BodyFormat::from_mime(&mime.parse().unwrap())
+    }
+
+    #[test]
+    fn formats_from_content_types() {
+        assert_eq!(format("application/json"), Some(BodyFormat::Json));
+        assert_eq!(format("application/problem+json"), Some(BodyFormat::Json));
+        assert_eq!(format("application/atom+xml"), Some(BodyFormat::Xml));
+        assert_eq!(format("text/xml"), Some(BodyFormat::Xml));
+        assert_eq!(format("application/x-ndjson"), Some(BodyFormat::Ndjson));
+        assert_eq!(format("application/x-yaml"), Some(BodyFormat::Yaml));
+        assert_eq!(format("text/html"), Some(BodyFormat::Html));
+        assert_eq!(format("text/event-stream"), Some(BodyFormat::Sse));
+        assert_eq!(format("image/svg+xml"), None);
+        assert_eq!(format("text/plain"), None);
+        assert_eq!(format("application/octet-stream"), None);
+    }
+
+    #[test]
+    fn formats_sniffed_from_the_body() {
+        assert_eq!(BodyFormat::sniff(" {\"a\": [1]}"), BodyFormat::Json);
+        assert_eq!(BodyFormat::sniff("[1, 2]"), BodyFormat::Json);
+        assert_eq!(
+            BodyFormat::sniff("{\"a\": 1}\n{\"a\": 2}\n"),
+            BodyFormat::Ndjson
+        );
+        assert_eq!(BodyFormat::sniff("{\"a\": 1}\n{\"a\": "), BodyFormat::Text);
+        assert_eq!(BodyFormat::sniff("{\"a\": 1"), BodyFormat::Text);
+        assert_eq!(
+            BodyFormat::sniff("<!DOCTYPE html><html><p>x</html>"),
+            BodyFormat::Html
+        );
+        assert_eq!(BodyFormat::sniff("<HTML><body>"), BodyFormat::Html);
+        assert_eq!(
+            BodyFormat::sniff("<?xml version=\"1.0\"?><a/>"),
+            BodyFormat::Xml
+        );
+        assert_eq!(BodyFormat::sniff("<a><b></a>"), BodyFormat::Text);
+        assert_eq!(BodyFormat::sniff("plain text"), BodyFormat::Text);
+        assert_eq!(BodyFormat::sniff(""), BodyFormat::Text);
+    }
+
+    #[test]
+    fn text_is_diffed_as_is() {
+        let profile = ResponseProfile {
+            skip_body: vec!["a".into()],
+            ..ResponseProfile::default()
+        };
+        assert_eq!(
+            BodyFormat::Text.filter("{\"a\": 1}", &profile).unwrap(),
+            "{\"a\": 1}"
+        );
+        assert_eq!(
+            BodyFormat::Json
+                .filter("{\"a\": 1, \"b\": 2}", &profile)
+                .unwrap(),
+            "{\n  \"b\": 2\n}"
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use super::filter_json;
+
+/// a graphql operation, sent as a json POST of `{query, variables, operationName}`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct GraphqlRequest {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub query: Option<String>,
+    /// path of a `.graphql` file holding the query
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub query_file: Option<String>,
+    #[serde(skip_serializing_if = "Map::is_empty", default)]
+    pub variables: Map<String, Value>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub operation_name: Option<String>,
+}
+
+/// the `data` and `errors` of a graphql response, rendered separately
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GraphqlText {
+    pub data: String,
+    pub errors: String,
+}
+
+impl GraphqlRequest {
+    pub(crate) fn validate(&self) -> Result<()> {
+        match (&self.query, &self.query_file) {
+            (Some(_), None) => Ok(()),
+            (None, Some(path)) => std::fs::metadata(path)
+                .map(|_| ())
+                .with_context(|| format!("graphql.query_file: {} 文件不存在", path)),
+            _ => Err(anyhow!("graphql 的 query 和 query_file 必须且只能设置一个")),
+        }
+    }
+
+    /// the json payload, `ExtraArgs::body` entries override the variables
+    pub async fn to_json(&self, extra_variables: &[(String, String)]) -> Result<Value> {
+        let query = match (&self.query, &self.query_file) {
+            (Some(query), _) => query.clone(),
+            (None, Some(path)) => tokio::fs::read_to_string(path)
+                .await
+                .with_context(|| format!("failed to read graphql query: {}", path))?,
+            (None, None) => return Err(anyhow!("graphql query is missing")),
+        };
+        let mut variables = self.variables.clone();
+        for (k, v) in extra_variables {
+            variables.insert(k.clone(), v.parse()?);
+        }
+
+        let mut payload = json!({ "query": query, "variables": variables });
+        if let Some(name) = &self.operation_name {
+            payload["operationName"] = json!(name);
+        }
+        Ok(payload)
+    }
+}
+
+/// split a graphql response body into `data` and `errors`, `skip` applies to the fields of `data`
+pub fn split_graphql(text: &str, skip: &[String]) -> Result<GraphqlText> {
+    let Ok(Value::Object(mut body)) = serde_json::from_str::<Value>(text) else {
+        return Ok(GraphqlText {
+            data: text.to_string(),
+            errors: String::new(),
+        });
+    };
+
+    let data = match body.remove("data") {
+        Some(data) => filter_json(&data.to_string(), skip)?,
+        None => String::new(),
+    };
+    let errors = match body.remove("errors") {
+        Some(errors) => serde_json::to_string_pretty(&errors)?,
+        None => String::new(),
+    };
+    Ok(GraphqlText { data, errors })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn extra_args_override_the_variables() {
+        let graphql = GraphqlRequest {
+            query: Some("query User($id: ID!) { user(id: $id) { name } }".to_string()),
+            variables: json!({ "id": 1, "lang": "en" })
+                .as_object()
+                .unwrap()
+                .clone(),
+            operation_name: Some("User".to_string()),
+            ..GraphqlRequest::default()
+        };
+        let payload = graphql
+            .to_json(&[("id".to_string(), "2".to_string())])
+            .await
+            .unwrap();
+        assert_eq!(
+            payload,
+            json!({
+                "query": "query User($id: ID!) { user(id: $id) { name } }",
+                "variables": { "id": 2, "lang": "en" },
+                "operationName": "User",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn the_query_can_come_from_a_file() {
+        let path = std::env::temp_dir().join(format!("netdiff-{}.graphql", std::process::id()));
+        std::fs::write(&path, "{ me { id } }").unwrap();
+        let graphql = GraphqlRequest {
+            query_file: Some(path.to_string_lossy().into_owned()),
+            ..GraphqlRequest::default()
+        };
+        assert!(graphql.validate().is_ok());
+        let payload = graphql.to_json(&[]).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            payload,
+            json!({ "query": "{ me { id } }", "variables": {} })
+        );
+    }
+
+    #[test]
+    fn query_and_query_file_are_exclusive() {
+        assert!(GraphqlRequest::default().validate().is_err());
+        let both = GraphqlRequest {
+            query: Some("{ a }".to_string()),
+            query_file: Some("a.graphql".to_string()),
+            ..GraphqlRequest::default()
+        };
+        assert!(both.validate().is_err());
+    }
+
+    #[test]
+    fn data_and_errors_are_split() {
+        let text = r#"{"data":{"user":{"name":"bob"},"at":1},"errors":[{"message":"partial"}]}"#;
+        let split = split_graphql(text, &["at".to_string()]).unwrap();
+        assert!(split.data.contains("\"name\": \"bob\""), "{}", split.data);
+        assert!(!split.data.contains("\"at\""));
+        assert!(split.errors.contains("\"message\": \"partial\""));
+
+        let split = split_graphql(r#"{"data":{"a":1}}"#, &[]).unwrap();
+        assert_eq!(split.errors, "");
+    }
+
+    #[test]
+    fn a_body_that_is_not_an_object_is_data() {
+        let split = split_graphql("Bad Gateway", &[]).unwrap();
+        assert_eq!(split.data, "Bad Gateway");
+        assert_eq!(split.errors, "");
+    }
+}
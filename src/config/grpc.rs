@@ -0,0 +1,233 @@
+use std::fmt::Write;
+
+use anyhow::{anyhow, bail, Context, Result};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use http::{
+    header::{CONTENT_TYPE, TE, USER_AGENT},
+    HeaderName, HeaderValue, Method,
+};
+use hyper::{body::HttpBody, Body, Client};
+use prost::Message;
+use prost_reflect::{DynamicMessage, MethodDescriptor};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::protobuf::load_descriptor_pool;
+use super::{filter_json, header_map_text, resolve_user_agent, RequestProfile, ResponseProfile};
+use crate::ExtraArgs;
+
+/// names of the grpc status codes, by code
+const STATUS_NAMES: [&str; 17] = [
+    "OK",
+    "CANCELLED",
+    "UNKNOWN",
+    "INVALID_ARGUMENT",
+    "DEADLINE_EXCEEDED",
+    "NOT_FOUND",
+    "ALREADY_EXISTS",
+    "PERMISSION_DENIED",
+    "RESOURCE_EXHAUSTED",
+    "FAILED_PRECONDITION",
+    "ABORTED",
+    "OUT_OF_RANGE",
+    "UNIMPLEMENTED",
+    "INTERNAL",
+    "UNAVAILABLE",
+    "DATA_LOSS",
+    "UNAUTHENTICATED",
+];
+
+/// a unary rpc, `url` is the server, e.g. `http://localhost:50051`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GrpcRequest {
+    /// path of a compiled `FileDescriptorSet`, e.g. from `protoc --include_imports -o`
+    pub descriptor_set: String,
+    /// `package.Service/Method`
+    pub method: String,
+    /// the request message as json
+    #[serde(skip_serializing_if = "Value::is_null", default)]
+    pub message: Value,
+}
+
+impl GrpcRequest {
+    pub(crate) fn validate(&self) -> Result<()> {
+        let method = self.method_descriptor()?;
+        self.to_message(&method, &[])?;
+        Ok(())
+    }
+
+    fn method_descriptor(&self) -> Result<MethodDescriptor> {
+        let pool = load_descriptor_pool(&self.descriptor_set, "grpc")?;
+        let (service, method) = self
+            .method
+            .trim_start_matches('/')
+            .rsplit_once(['/', '.'])
+            .ok_or_else(|| anyhow!("grpc.method: {} 应该是 package.Service/Method", self.method))?;
+        let service = pool
+            .get_service_by_name(service)
+            .ok_or_else(|| anyhow!("grpc.method: 找不到 service {}", service))?;
+        let method = service
+            .methods()
+            .find(|m| m.name() == method)
+            .ok_or_else(|| anyhow!("grpc.method: {} 中找不到 {}", service.full_name(), method))?;
+        if method.is_client_streaming() || method.is_server_streaming() {
+            bail!("grpc.method: {} 不是 unary rpc", method.full_name());
+        }
+        Ok(method)
+    }
+
+    /// the json message with the extra body args set on top
+    fn to_message(
+        &self,
+        method: &MethodDescriptor,
+        extra_body: &[(String, String)],
+    ) -> Result<DynamicMessage> {
+        let mut json = match &self.message {
+            Value::Null => serde_json::json!({}),
+            v => v.clone(),
+        };
+        for (k, v) in extra_body {
+            json[k] = v.parse().unwrap_or_else(|_| Value::String(v.clone()));
+        }
+        DynamicMessage::deserialize(method.input(), json)
+            .with_context(|| format!("grpc.message 不是有效的 {}", method.input().full_name()))
+    }
+}
+
+impl RequestProfile {
+    /// the http status, grpc status, headers and trailers followed by the response message as json
+    pub async fn invoke(
+        &self,
+        extra_args: &ExtraArgs,
+        profile: &ResponseProfile,
+    ) -> Result<String> {
+        let grpc = self
+            .grpc
+            .as_ref()
+            .ok_or_else(|| anyhow!("{} is not a grpc profile", self.url))?;
+        if !extra_args.query.is_empty() || !extra_args.files.is_empty() {
+            bail!("grpc takes header and message field args only, not query or file args");
+        }
+        let method = grpc.method_descriptor()?;
+        let message = grpc.to_message(&method, &extra_args.body)?;
+
+        let mut body = BytesMut::new();
+        let encoded = message.encode_to_vec();
+        body.put_u8(0);
+        body.put_u32(encoded.len() as u32);
+        body.put_slice(&encoded);
+
+        let path = format!("/{}/{}", method.parent_service().full_name(), method.name());
+        let url = self.url.join(&path)?;
+        let mut request = hyper::Request::builder()
+            .method(Method::POST)
+            .uri(url.as_str())
+            .body(Body::from(body.freeze()))?;
+        let headers = request.headers_mut();
+        for (k, v) in &self.headers {
+            headers.insert(k, v.clone());
+        }
+        for (k, v) in &extra_args.headers {
+            headers.insert(
+                HeaderName::from_bytes(k.as_bytes())?,
+                HeaderValue::from_str(v)?,
+            );
+        }
+        let user_agent = extra_args.user_agent.as_ref().or(self.user_agent.as_ref());
+        if let Some(user_agent) = user_agent {
+            headers.insert(
+                USER_AGENT,
+                HeaderValue::from_str(resolve_user_agent(user_agent))?,
+            );
+        }
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
+        headers.insert(TE, HeaderValue::from_static("trailers"));
+
+        let connector = hyper_rustls::HttpsConnectorBuilder::new()
+            .with_webpki_roots()
+            .https_or_http()
+            .enable_http2()
+            .build();
+        let client: Client<_, Body> = Client::builder().http2_only(true).build(connector);
+        let call = async {
+            let res = client.request(request).await?;
+            let (parts, mut body) = res.into_parts();
+            let mut data = BytesMut::new();
+            while let Some(chunk) = body.data().await {
+                data.extend_from_slice(&chunk?);
+            }
+            let trailers = body.trailers().await?.unwrap_or_default();
+            Ok::<_, anyhow::Error>((parts, data.freeze(), trailers))
+        };
+        let (parts, data, trailers) = match self.retry.timeout() {
+            Some(limit) => tokio::time::timeout(limit, call)
+                .await
+                .map_err(|_| anyhow!("{} timed out", url))?,
+            None => call.await,
+        }
+        .with_context(|| format!("grpc {} failed", url))?;
+
+        let mut output = String::new();
+        writeln!(output, "{:?} {}", parts.version, parts.status)?;
+        // a trailers-only response carries the status in its headers
+        let status = trailers
+            .get("grpc-status")
+            .or_else(|| parts.headers.get("grpc-status"))
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<usize>().ok());
+        match status {
+            Some(code) => writeln!(
+                output,
+                "grpc-status: {} {}",
+                code,
+                STATUS_NAMES.get(code).unwrap_or(&"")
+            )?,
+            None => writeln!(output, "grpc-status: missing")?,
+        }
+        if let Some(message) = trailers
+            .get("grpc-message")
+            .or_else(|| parts.headers.get("grpc-message"))
+        {
+            writeln!(
+                output,
+                "grpc-message: {}",
+                String::from_utf8_lossy(message.as_bytes())
+            )?;
+        }
+        write!(
+            output,
+            "{}",
+            header_map_text(&parts.headers, &profile.skip_headers)
+        )?;
+        let trailer_text = header_map_text(&trailers, &profile.skip_headers);
+        if !trailer_text.is_empty() {
+            write!(output, "trailers:\n{}", trailer_text)?;
+        }
+        for message in decode_frames(data)? {
+            let message = DynamicMessage::decode(method.output(), message)?;
+            let json = serde_json::to_string(&message)?;
+            writeln!(output, "{}", filter_json(&json, &profile.skip_body)?)?;
+        }
+        Ok(output)
+    }
+}
+
+/// the messages of a grpc body, each after a compressed flag and a 4 byte length
+fn decode_frames(mut data: Bytes) -> Result<Vec<Bytes>> {
+    let mut messages = vec![];
+    while data.has_remaining() {
+        if data.remaining() < 5 {
+            bail!("grpc response is cut off");
+        }
+        let compressed = data.get_u8();
+        let len = data.get_u32() as usize;
+        if compressed != 0 {
+            bail!("compressed grpc messages are not supported");
+        }
+        if data.remaining() < len {
+            bail!("grpc response is cut off");
+        }
+        messages.push(data.split_to(len));
+    }
+    Ok(messages)
+}
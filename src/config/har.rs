@@ -0,0 +1,389 @@
+use std::collections::HashSet;
+
+use anyhow::{anyhow, Context, Result};
+use http::{
+    header::{CONTENT_TYPE, USER_AGENT},
+    HeaderMap, HeaderName, HeaderValue, Method,
+};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use url::Url;
+
+use super::{
+    curl::data_to_body, DiffConfig, DiffProfile, MultipartPart, RequestBody, RequestConfig,
+    RequestProfile, ResponseProfile,
+};
+
+/// headers set by the browser or the connection, reqwest sends its own
+const SKIPPED_HEADERS: &[&str] = &[
+    "host",
+    "content-length",
+    "connection",
+    "accept-encoding",
+    "keep-alive",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// which entries of a har file become profiles, an empty list matches everything
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HarFilter {
+    /// hosts to keep, subdomains match as well
+    pub domains: Vec<String>,
+    pub methods: Vec<String>,
+    /// substrings of the response content type, e.g. `json`
+    pub content_types: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Har {
+    log: HarLog,
+}
+
+#[derive(Deserialize, Debug)]
+struct HarLog {
+    entries: Vec<HarEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+struct HarEntry {
+    request: HarRequest,
+    #[serde(default)]
+    response: Option<HarResponse>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct HarRequest {
+    method: String,
+    url: String,
+    #[serde(default)]
+    headers: Vec<HarHeader>,
+    #[serde(default)]
+    post_data: Option<HarPostData>,
+}
+
+#[derive(Deserialize, Debug)]
+struct HarHeader {
+    name: String,
+    value: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct HarPostData {
+    #[serde(default)]
+    mime_type: String,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    params: Vec<HarParam>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct HarParam {
+    name: String,
+    #[serde(default)]
+    value: Option<String>,
+    #[serde(default)]
+    file_name: Option<String>,
+    #[serde(default)]
+    content_type: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct HarResponse {
+    #[serde(default)]
+    content: Option<HarContent>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct HarContent {
+    #[serde(default)]
+    mime_type: String,
+}
+
+impl HarFilter {
+    fn matches(&self, entry: &HarEntry, url: &Url) -> bool {
+        let host = url.host_str().unwrap_or_default();
+        let domain = self.domains.is_empty()
+            || self.domains.iter().any(|d| {
+                let d = d.trim_start_matches('.');
+                host == d || host.ends_with(&format!(".{}", d))
+            });
+        let method = self.methods.is_empty()
+            || self
+                .methods
+                .iter()
+                .any(|m| m.eq_ignore_ascii_case(&entry.request.method));
+        let mime_type = entry
+            .response
+            .as_ref()
+            .and_then(|r| r.content.as_ref())
+            .map(|c| c.mime_type.to_lowercase())
+            .unwrap_or_default();
+        let content_type = self.content_types.is_empty()
+            || self
+                .content_types
+                .iter()
+                .any(|t| mime_type.contains(&t.to_lowercase()));
+        domain && method && content_type
+    }
+}
+
+/// one profile per matching http(s) entry of a har file, named after the method and the path,
+/// an entry that can't be replayed is skipped with a warning
+pub fn parse_har(text: &str, filter: &HarFilter) -> Result<Vec<(String, RequestProfile)>> {
+    let har: Har = serde_json::from_str(text).context("不是有效的 har 文件")?;
+    let mut names: HashSet<String> = HashSet::new();
+    let mut profiles = vec![];
+
+    for (i, entry) in har.log.entries.iter().enumerate() {
+        // data:, blob:, ws: and extension urls are captured too but are not http requests
+        let url = match Url::parse(&entry.request.url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => url,
+            Ok(_) => continue,
+            Err(e) => {
+                eprintln!("skipping har entry {}: {}: {}", i, entry.request.url, e);
+                continue;
+            }
+        };
+        if !filter.matches(entry, &url) {
+            continue;
+        }
+        let profile = match entry_to_profile(&entry.request, url) {
+            Ok(profile) => profile,
+            Err(e) => {
+                eprintln!("skipping har entry {}: {}: {:#}", i, entry.request.url, e);
+                continue;
+            }
+        };
+
+        let name = unique_name(
+            &mut names,
+            profile_name(&profile.method, profile.url.path()),
+        );
+        profiles.push((name, profile));
+    }
+    Ok(profiles)
+}
+
+fn entry_to_profile(req: &HarRequest, mut url: Url) -> Result<RequestProfile> {
+    let method: Method = req.method.parse()?;
+
+    let mut params = Map::new();
+    for (k, v) in url.query_pairs() {
+        params.insert(k.to_string(), Value::String(v.into_owned()));
+    }
+    url.set_query(None);
+    url.set_fragment(None);
+
+    let mut headers = HeaderMap::new();
+    let mut user_agent = None;
+    for h in &req.headers {
+        let name = h.name.to_lowercase();
+        // http/2 pseudo headers such as `:authority`
+        if name.starts_with(':') || SKIPPED_HEADERS.contains(&name.as_str()) {
+            continue;
+        }
+        if name == USER_AGENT.as_str() {
+            user_agent = Some(h.value.clone());
+            continue;
+        }
+        headers.append(
+            HeaderName::from_bytes(name.as_bytes())?,
+            HeaderValue::from_str(&h.value)?,
+        );
+    }
+
+    let body = match &req.post_data {
+        Some(data) => post_data_to_body(data, &mut headers)?,
+        None => None,
+    };
+
+    Ok(RequestProfile::new(
+        method,
+        url,
+        Value::Object(params),
+        headers,
+        body,
+        user_agent,
+    ))
+}
+
+fn post_data_to_body(data: &HarPostData, headers: &mut HeaderMap) -> Result<Option<RequestBody>> {
+    if !headers.contains_key(CONTENT_TYPE) && !data.mime_type.is_empty() {
+        headers.insert(CONTENT_TYPE, HeaderValue::from_str(&data.mime_type)?);
+    }
+    if let Some(text) = data.text.as_ref().filter(|t| !t.is_empty()) {
+        return Ok(Some(data_to_body(text.clone(), headers)));
+    }
+    if data.params.is_empty() {
+        return Ok(None);
+    }
+
+    if data.mime_type.starts_with("multipart/form-data") {
+        // the file contents are not part of the capture, the parts keep their value if any
+        headers.remove(CONTENT_TYPE);
+        let parts = data
+            .params
+            .iter()
+            .map(|p| MultipartPart {
+                name: p.name.clone(),
+                value: Some(p.value.clone().unwrap_or_default()),
+                file: None,
+                filename: p.file_name.clone(),
+                content_type: p.content_type.clone(),
+            })
+            .collect();
+        return Ok(Some(RequestBody::Multipart(parts)));
+    }
+
+    headers.remove(CONTENT_TYPE);
+    let form = data
+        .params
+        .iter()
+        .map(|p| (p.name.clone(), json!(p.value.clone().unwrap_or_default())))
+        .collect::<Map<_, _>>();
+    Ok(Some(RequestBody::Form(Value::Object(form))))
+}
+
+/// `base`, or `base-2`, `base-3`... if it is taken, the name is added to `names`
+pub(super) fn unique_name(names: &mut HashSet<String>, base: String) -> String {
+    // `get-a-2` may be the name of `GET /a/2` as well as of the second `GET /a`
+    let mut name = base.clone();
+    let mut n = 1;
+    while names.contains(&name) {
+        n += 1;
+        name = format!("{}-{}", base, n);
+    }
+    names.insert(name.clone());
+    name
+}
+
+/// `get-api-users` for `GET /api/users`
+pub(super) fn profile_name(method: &Method, path: &str) -> String {
+    let raw = format!("{} {}", method, path).to_lowercase();
+    let mut name = String::new();
+    for c in raw.chars() {
+        if c.is_ascii_alphanumeric() {
+            name.push(c);
+        } else if !name.ends_with('-') {
+            name.push('-');
+        }
+    }
+    let name = name.trim_end_matches('-');
+    if name.contains('-') {
+        name.to_string()
+    } else {
+        format!("{}-root", name)
+    }
+}
+
+/// `url` with the scheme, host and port of `base`, the path of `base` is used as a prefix
+pub fn retarget_url(url: &Url, base: &Url) -> Url {
+    let mut target = base.clone();
+    let prefix = base.path().trim_end_matches('/');
+    target.set_path(&format!("{}{}", prefix, url.path()));
+    target.set_query(url.query());
+    target
+}
+
+impl RequestConfig {
+    /// build a config from a har capture
+    pub fn from_har(text: &str, filter: &HarFilter) -> Result<Self> {
+        Ok(Self::new(parse_har(text, filter)?.into_iter().collect()))
+    }
+}
+
+impl DiffConfig {
+    /// build a config from a har capture, req2 is sent to `target` or to the same url if not set
+    pub fn from_har(text: &str, filter: &HarFilter, target: Option<&Url>) -> Result<Self> {
+        if let Some(target) = target {
+            if target.cannot_be_a_base() {
+                return Err(anyhow!("{} 不是有效的 base url", target));
+            }
+        }
+        let profiles = parse_har(text, filter)?
+            .into_iter()
+            .map(|(name, req1)| {
+                let mut req2 = req1.clone();
+                if let Some(target) = target {
+                    req2.url = retarget_url(&req1.url, target);
+                }
+                (
+                    name,
+                    DiffProfile::new(req1, req2, ResponseProfile::default()),
+                )
+            })
+            .collect();
+        Ok(Self::new(profiles))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn har(entries: Value) -> String {
+        json!({ "log": { "entries": entries } }).to_string()
+    }
+
+    fn entry(method: &str, url: &str) -> Value {
+        json!({ "request": { "method": method, "url": url } })
+    }
+
+    #[test]
+    fn only_http_entries_become_profiles() {
+        let text = har(json!([
+            entry("GET", "data:text/plain,hi"),
+            entry("GET", "blob:https://a.test/1"),
+            entry("GET", "chrome-extension://abc/x.js"),
+            entry("GET", "ws://a.test/socket"),
+            entry("GET", "https://a.test/x"),
+        ]));
+        let profiles = parse_har(&text, &HarFilter::default()).unwrap();
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].0, "get-x");
+    }
+
+    #[test]
+    fn an_invalid_header_skips_only_its_entry() {
+        let mut bad = entry("GET", "https://a.test/bad");
+        bad["request"]["headers"] = json!([{ "name": "x-name", "value": "caf\u{e9}\n" }]);
+        let text = har(json!([bad, entry("GET", "https://a.test/ok")]));
+        let profiles = parse_har(&text, &HarFilter::default()).unwrap();
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].0, "get-ok");
+    }
+
+    #[test]
+    fn dedup_suffixes_dont_collide_with_paths() {
+        let text = har(json!([
+            entry("GET", "https://a.test/a"),
+            entry("GET", "https://a.test/a"),
+            entry("GET", "https://a.test/a/2"),
+        ]));
+        let names: Vec<String> = parse_har(&text, &HarFilter::default())
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["get-a", "get-a-2", "get-a-2-2"]);
+    }
+
+    #[test]
+    fn query_values_stay_strings() {
+        let text = har(json!([entry(
+            "GET",
+            "https://a.test/x?v=1.10&e=1e3&id=12345678901234567890123&n=null"
+        )]));
+        let profiles = parse_har(&text, &HarFilter::default()).unwrap();
+        assert_eq!(
+            profiles[0].1.params,
+            json!({ "v": "1.10", "e": "1e3", "id": "12345678901234567890123", "n": "null" })
+        );
+    }
+}
@@ -0,0 +1,646 @@
+use std::collections::HashSet;
+
+use anyhow::{anyhow, bail, Context, Result};
+use roxmltree::{Document, Node, NodeId, ParsingOptions};
+use scraper::{Html, Selector};
+use serde::Deserialize;
+use serde_yaml::Value;
+
+/// elements whose text is kept as is
+const RAW_TEXT_ELEMENTS: [&str; 4] = ["pre", "textarea", "script", "style"];
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// pretty printed xml with sorted attributes, nodes matched by `skip` left out
+pub fn filter_xml(text: &str, skip: &[String]) -> Result<String> {
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(text, options).context("body is not valid xml")?;
+    let mut skipped = Skipped::default();
+    for path in skip {
+        XPath::parse(path)?.select(&doc, &mut skipped);
+    }
+    let mut out = Printer::default();
+    for node in doc.root().children() {
+        print_xml(node, &skipped, &mut out);
+    }
+    Ok(out.finish())
+}
+
+/// normalized html dom, elements matched by the `skip` css selectors left out
+pub fn filter_html(text: &str, skip: &[String]) -> Result<String> {
+    let html = Html::parse_document(text);
+    let mut skipped = HashSet::new();
+    for selector in skip {
+        skipped.extend(html.select(&parse_selector(selector)?).map(|e| e.id()));
+    }
+    let mut out = Printer::default();
+    for node in html.tree.root().children() {
+        print_html(node, &skipped, false, &mut out);
+    }
+    Ok(out.finish())
+}
+
+/// every yaml document re-serialized with sorted keys, top level `skip` keys left out
+pub fn filter_yaml(text: &str, skip: &[String]) -> Result<String> {
+    let mut docs = vec![];
+    for doc in serde_yaml::Deserializer::from_str(text) {
+        let mut value = Value::deserialize(doc).context("body is not valid yaml")?;
+        if let Value::Mapping(map) = &mut value {
+            for k in skip {
+                map.remove(k.as_str());
+            }
+        }
+        docs.push(serde_yaml::to_string(&sort_yaml(value))?);
+    }
+    Ok(docs.join("---\n"))
+}
+
+pub(super) fn validate_xpath(path: &str) -> Result<()> {
+    XPath::parse(path).map(|_| ())
+}
+
+pub(super) fn parse_selector(selector: &str) -> Result<Selector> {
+    Selector::parse(selector).map_err(|e| anyhow!("{} 不是有效的 css selector: {}", selector, e))
+}
+
+fn sort_yaml(value: Value) -> Value {
+    match value {
+        Value::Mapping(map) => {
+            let mut entries: Vec<(Value, Value)> = map.into_iter().collect();
+            entries.sort_by_cached_key(|(k, _)| serde_yaml::to_string(k).unwrap_or_default());
+            Value::Mapping(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k, sort_yaml(v)))
+                    .collect(),
+            )
+        }
+        Value::Sequence(seq) => Value::Sequence(seq.into_iter().map(sort_yaml).collect()),
+        Value::Tagged(mut tagged) => {
+            tagged.value = sort_yaml(tagged.value);
+            Value::Tagged(tagged)
+        }
+        v => v,
+    }
+}
+
+#[derive(Default)]
+struct Printer {
+    lines: Vec<String>,
+    depth: usize,
+}
+
+impl Printer {
+    fn line(&mut self, text: impl AsRef<str>) {
+        self.lines
+            .push(format!("{}{}", "  ".repeat(self.depth), text.as_ref()));
+    }
+
+    fn finish(self) -> String {
+        let mut text = self.lines.join("\n");
+        text.push('\n');
+        text
+    }
+}
+
+/// element names and attributes in the prefixes the document uses
+fn xml_name(node: Node, namespace: Option<&str>, name: &str) -> String {
+    match namespace.and_then(|ns| node.lookup_prefix(ns)) {
+        Some(prefix) if !prefix.is_empty() => format!("{}:{}", prefix, name),
+        _ => name.to_string(),
+    }
+}
+
+fn xml_start_tag(node: Node, skipped: &Skipped) -> String {
+    let tag = node.tag_name();
+    let parent = node.parent().filter(|p| p.is_element());
+    let mut attrs: Vec<String> = node
+        .namespaces()
+        .filter(|ns| !parent.is_some_and(|p| p.namespaces().any(|n| n == *ns)))
+        .map(|ns| match ns.name() {
+            Some(prefix) => format!("xmlns:{}=\"{}\"", prefix, escape_attr(ns.uri())),
+            None => format!("xmlns=\"{}\"", escape_attr(ns.uri())),
+        })
+        .collect();
+    let mut plain: Vec<(String, &str)> = node
+        .attributes()
+        .map(|a| (xml_name(node, a.namespace(), a.name()), a.value()))
+        .filter(|(name, _)| !skipped.attrs.contains(&(node.id(), name.clone())))
+        .collect();
+    plain.sort();
+    attrs.extend(
+        plain
+            .into_iter()
+            .map(|(name, value)| format!("{}=\"{}\"", name, escape_attr(value))),
+    );
+    let name = xml_name(node, tag.namespace(), tag.name());
+    match attrs.is_empty() {
+        true => name,
+        false => format!("{} {}", name, attrs.join(" ")),
+    }
+}
+
+fn print_xml(node: Node, skipped: &Skipped, out: &mut Printer) {
+    if skipped.nodes.contains(&node.id()) {
+        return;
+    }
+    if node.is_text() {
+        let text = node.text().unwrap_or_default().trim();
+        if !text.is_empty() {
+            out.line(escape_text(text));
+        }
+    } else if node.is_comment() {
+        out.line(format!("<!--{}-->", node.text().unwrap_or_default()));
+    } else if let Some(pi) = node.pi() {
+        match pi.value {
+            Some(value) => out.line(format!("<?{} {}?>", pi.target, value)),
+            None => out.line(format!("<?{}?>", pi.target)),
+        }
+    } else if node.is_element() {
+        let tag = xml_start_tag(node, skipped);
+        let name = tag.split(' ').next().unwrap_or_default().to_string();
+        let children: Vec<Node> = node
+            .children()
+            .filter(|c| !skipped.nodes.contains(&c.id()))
+            .filter(|c| !c.is_text() || !c.text().unwrap_or_default().trim().is_empty())
+            .collect();
+        match children.as_slice() {
+            [] => out.line(format!("<{}/>", tag)),
+            [text] if text.is_text() => out.line(format!(
+                "<{}>{}</{}>",
+                tag,
+                escape_text(text.text().unwrap_or_default().trim()),
+                name
+            )),
+            _ => {
+                out.line(format!("<{}>", tag));
+                out.depth += 1;
+                for child in children {
+                    print_xml(child, skipped, out);
+                }
+                out.depth -= 1;
+                out.line(format!("</{}>", name));
+            }
+        }
+    }
+}
+
+type HtmlNode<'a> = ego_tree::NodeRef<'a, scraper::Node>;
+
+fn print_html(node: HtmlNode, skipped: &HashSet<ego_tree::NodeId>, raw: bool, out: &mut Printer) {
+    if skipped.contains(&node.id()) {
+        return;
+    }
+    match node.value() {
+        scraper::Node::Text(text) if raw => {
+            for line in text.trim_matches('\n').lines() {
+                out.line(line);
+            }
+        }
+        scraper::Node::Text(text) => {
+            let text = collapse_whitespace(text);
+            if !text.is_empty() {
+                out.line(escape_text(&text));
+            }
+        }
+        scraper::Node::Comment(comment) => out.line(format!("<!--{}-->", &**comment)),
+        scraper::Node::Doctype(doctype) => out.line(format!("<!DOCTYPE {}>", doctype.name())),
+        scraper::Node::ProcessingInstruction(pi) => {
+            out.line(format!("<?{} {}?>", pi.target, &**pi))
+        }
+        scraper::Node::Element(el) => {
+            let name = el.name();
+            let mut attrs: Vec<(&str, &str)> = el.attrs().collect();
+            attrs.sort();
+            let tag = attrs.iter().fold(name.to_string(), |mut tag, (k, v)| {
+                tag.push_str(&format!(" {}=\"{}\"", k, escape_attr(v)));
+                tag
+            });
+            if VOID_ELEMENTS.contains(&name) {
+                out.line(format!("<{}>", tag));
+                return;
+            }
+            let raw = raw || RAW_TEXT_ELEMENTS.contains(&name);
+            let children: Vec<HtmlNode> = node
+                .children()
+                .filter(|c| !skipped.contains(&c.id()))
+                .filter(|c| match c.value() {
+                    scraper::Node::Text(t) => !t.trim().is_empty(),
+                    _ => true,
+                })
+                .collect();
+            match children.as_slice() {
+                [] => out.line(format!("<{}></{}>", tag, name)),
+                [child] if !raw && child.value().is_text() => {
+                    let text = child
+                        .value()
+                        .as_text()
+                        .map_or(String::new(), |t| escape_text(&collapse_whitespace(t)));
+                    out.line(format!("<{}>{}</{}>", tag, text, name))
+                }
+                _ => {
+                    out.line(format!("<{}>", tag));
+                    out.depth += 1;
+                    for child in children {
+                        print_html(child, skipped, raw, out);
+                    }
+                    out.depth -= 1;
+                    out.line(format!("</{}>", name));
+                }
+            }
+        }
+        scraper::Node::Document | scraper::Node::Fragment => {
+            for child in node.children() {
+                print_html(child, skipped, raw, out);
+            }
+        }
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn escape_attr(text: &str) -> String {
+    escape_text(text).replace('"', "&quot;")
+}
+
+/// nodes and `(element, attribute)` pairs left out of the output
+#[derive(Default)]
+struct Skipped {
+    nodes: HashSet<NodeId>,
+    attrs: HashSet<(NodeId, String)>,
+}
+
+/// the xpath subset skip rules use: `/a/b`, `//b`, `*`, `text()`, `comment()`, a final `@attr`,
+/// and `[n]`, `[@attr]`, `[@attr='v']` predicates; paths without a leading `/` match anywhere
+#[derive(Debug)]
+struct XPath {
+    steps: Vec<Step>,
+    attr: Option<String>,
+}
+
+#[derive(Debug)]
+struct Step {
+    descendant: bool,
+    test: NodeTest,
+    predicates: Vec<Predicate>,
+}
+
+#[derive(Debug)]
+enum NodeTest {
+    Any,
+    Name(String),
+    Text,
+    Comment,
+}
+
+#[derive(Debug)]
+enum Predicate {
+    Position(usize),
+    HasAttr(String),
+    AttrEq(String, String),
+}
+
+impl XPath {
+    fn parse(path: &str) -> Result<Self> {
+        let invalid = |reason: &str| anyhow!("{} 不是有效的 xpath: {}", path, reason);
+        let mut rest = path.trim();
+        if rest.is_empty() {
+            return Err(invalid("empty path"));
+        }
+        let mut descendant = !rest.starts_with('/');
+        let mut steps = vec![];
+        let mut attr = None;
+        loop {
+            if let Some(r) = rest.strip_prefix("//") {
+                descendant = true;
+                rest = r;
+            } else if let Some(r) = rest.strip_prefix('/') {
+                rest = r;
+            }
+            let end = step_end(rest);
+            let (step, r) = rest.split_at(end);
+            rest = r;
+            if attr.is_some() {
+                return Err(invalid("attributes have no children"));
+            }
+            if let Some(name) = step.strip_prefix('@') {
+                if name.is_empty() {
+                    return Err(invalid("missing attribute name"));
+                }
+                attr = Some(name.to_string());
+            } else {
+                steps.push(Step::parse(step, descendant).map_err(|e| invalid(&e.to_string()))?);
+            }
+            descendant = false;
+            if rest.is_empty() {
+                break;
+            }
+        }
+        Ok(Self { steps, attr })
+    }
+
+    fn select(&self, doc: &Document, skipped: &mut Skipped) {
+        let mut nodes = vec![doc.root()];
+        for step in &self.steps {
+            nodes = step.apply(&nodes);
+        }
+        match &self.attr {
+            None => skipped.nodes.extend(nodes.iter().map(|n| n.id())),
+            Some(attr) => {
+                let nodes = match self.steps.is_empty() {
+                    true => doc.descendants().filter(|n| n.is_element()).collect(),
+                    false => nodes,
+                };
+                for node in nodes {
+                    for a in node.attributes() {
+                        let name = xml_name(node, a.namespace(), a.name());
+                        if attr == "*" || *attr == name || attr == a.name() {
+                            skipped.attrs.insert((node.id(), name));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// a step ends at the next `/` outside of a predicate
+fn step_end(text: &str) -> usize {
+    let mut depth = 0;
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '[') => depth += 1,
+            (None, ']') => depth -= 1,
+            (None, '/') if depth == 0 => return i,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// the `]` closing the predicate `text` starts with, one in a quoted value doesn't count
+fn predicate_end(text: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, ']') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+impl Step {
+    fn parse(step: &str, descendant: bool) -> Result<Self> {
+        let (test, mut rest) = match step.find('[') {
+            Some(i) => step.split_at(i),
+            None => (step, ""),
+        };
+        let test = match test.trim() {
+            "" => bail!("empty step"),
+            "*" | "node()" => NodeTest::Any,
+            "text()" => NodeTest::Text,
+            "comment()" => NodeTest::Comment,
+            name if name.contains(['(', ')', '@', '[', ']', ' ']) => {
+                bail!("unsupported step {}", name)
+            }
+            name => NodeTest::Name(name.to_string()),
+        };
+        let mut predicates = vec![];
+        while !rest.is_empty() {
+            let end = predicate_end(rest).ok_or_else(|| anyhow!("unclosed predicate"))?;
+            predicates.push(Predicate::parse(rest[1..end].trim())?);
+            rest = rest[end + 1..].trim_start();
+            if !rest.is_empty() && !rest.starts_with('[') {
+                bail!("unexpected {}", rest);
+            }
+        }
+        Ok(Self {
+            descendant,
+            test,
+            predicates,
+        })
+    }
+
+    /// `//a[1]` is the first `a` child of any node, like in xpath
+    fn apply<'a, 'input>(&self, context: &[Node<'a, 'input>]) -> Vec<Node<'a, 'input>> {
+        let mut parents = vec![];
+        for node in context {
+            match self.descendant {
+                true => parents.extend(node.descendants()),
+                false => parents.push(*node),
+            }
+        }
+        let mut seen = HashSet::new();
+        let mut selected = vec![];
+        for parent in parents {
+            let candidates: Vec<Node> = parent.children().filter(|c| self.matches(*c)).collect();
+            for (i, node) in candidates.iter().enumerate() {
+                if self.predicates.iter().all(|p| p.matches(*node, i + 1)) && seen.insert(node.id())
+                {
+                    selected.push(*node);
+                }
+            }
+        }
+        selected
+    }
+
+    fn matches(&self, node: Node) -> bool {
+        match &self.test {
+            NodeTest::Any => node.is_element(),
+            NodeTest::Text => node.is_text(),
+            NodeTest::Comment => node.is_comment(),
+            NodeTest::Name(name) => {
+                let tag = node.tag_name();
+                node.is_element()
+                    && (tag.name() == name || xml_name(node, tag.namespace(), tag.name()) == *name)
+            }
+        }
+    }
+}
+
+impl Predicate {
+    fn parse(text: &str) -> Result<Self> {
+        if let Ok(n) = text.parse::<usize>() {
+            if n == 0 {
+                bail!("positions start at 1");
+            }
+            return Ok(Self::Position(n));
+        }
+        let Some(attr) = text.strip_prefix('@') else {
+            bail!("unsupported predicate [{}]", text);
+        };
+        match attr.split_once('=') {
+            None => Ok(Self::HasAttr(attr.trim().to_string())),
+            Some((name, value)) => {
+                let value = value.trim();
+                let unquoted = value
+                    .strip_prefix('\'')
+                    .and_then(|v| v.strip_suffix('\''))
+                    .or_else(|| value.strip_prefix('"').and_then(|v| v.strip_suffix('"')))
+                    .ok_or_else(|| anyhow!("{} has to be quoted", value))?;
+                Ok(Self::AttrEq(name.trim().to_string(), unquoted.to_string()))
+            }
+        }
+    }
+
+    fn matches(&self, node: Node, position: usize) -> bool {
+        let attr = |name: &str| {
+            node.attributes()
+                .find(|a| a.name() == name || xml_name(node, a.namespace(), a.name()) == name)
+                .map(|a| a.value())
+        };
+        match self {
+            Self::Position(n) => position == *n,
+            Self::HasAttr(name) => attr(name).is_some(),
+            Self::AttrEq(name, value) => attr(name) == Some(value.as_str()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn skip(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|p| p.to_string()).collect()
+    }
+
+    const FEED: &str = r#"<?xml version="1.0"?>
+<feed xmlns:m="urn:meta" updated="2024">
+  <!-- generated -->
+  <entry id="1" m:rev="3"><title>a</title><m:at>1</m:at></entry>
+  <entry id="2"><title>b]</title><link title="x]" href="/b"/></entry>
+</feed>"#;
+
+    #[test]
+    fn xml_is_printed_with_sorted_attributes() {
+        let xml = filter_xml(r#"<a z="1" b="&quot;"><b>x &amp; y</b><c/></a>"#, &[]).unwrap();
+        assert_eq!(
+            xml,
+            "<a b=\"&quot;\" z=\"1\">\n  <b>x &amp; y</b>\n  <c/>\n</a>\n"
+        );
+    }
+
+    #[test]
+    fn nodes_are_removed() {
+        let xml = filter_xml(FEED, &skip(&["//m:at", "/feed/comment()", "entry[2]/link"])).unwrap();
+        assert!(!xml.contains("m:at"), "{}", xml);
+        assert!(!xml.contains("generated"), "{}", xml);
+        assert!(!xml.contains("<link"), "{}", xml);
+        assert!(xml.contains("<title>b]</title>"), "{}", xml);
+
+        let xml = filter_xml(FEED, &skip(&["//entry/title/text()"])).unwrap();
+        assert!(
+            xml.contains("<title/>") && !xml.contains("<title>a"),
+            "{}",
+            xml
+        );
+    }
+
+    #[test]
+    fn attributes_are_removed() {
+        let xml = filter_xml(FEED, &skip(&["@updated", "//entry/@m:rev"])).unwrap();
+        assert!(xml.contains("<feed xmlns:m=\"urn:meta\">"), "{}", xml);
+        assert!(xml.contains("<entry id=\"1\">"), "{}", xml);
+
+        let xml = filter_xml(FEED, &skip(&["entry/@*"])).unwrap();
+        assert!(xml.contains("<entry>") && !xml.contains("id="), "{}", xml);
+    }
+
+    #[test]
+    fn predicates_select_by_position_and_attribute() {
+        let xml = filter_xml(FEED, &skip(&["/feed/entry[1]"])).unwrap();
+        assert!(
+            !xml.contains("id=\"1\"") && xml.contains("id=\"2\""),
+            "{}",
+            xml
+        );
+
+        let xml = filter_xml(FEED, &skip(&["//entry[@m:rev]"])).unwrap();
+        assert!(
+            !xml.contains("id=\"1\"") && xml.contains("id=\"2\""),
+            "{}",
+            xml
+        );
+
+        let xml = filter_xml(FEED, &skip(&["//entry[@id='2']"])).unwrap();
+        assert!(
+            xml.contains("id=\"1\"") && !xml.contains("id=\"2\""),
+            "{}",
+            xml
+        );
+    }
+
+    #[test]
+    fn brackets_in_quoted_predicate_values() {
+        let xml = filter_xml(FEED, &skip(&[r#"//link[@title="x]"]"#])).unwrap();
+        assert!(!xml.contains("<link"), "{}", xml);
+        let xml = filter_xml(FEED, &skip(&["//link[@title='x]'][1]/@href"])).unwrap();
+        assert!(xml.contains("<link title=\"x]\"/>"), "{}", xml);
+    }
+
+    #[test]
+    fn invalid_xpaths_are_rejected() {
+        for path in [
+            "",
+            "a[",
+            "a[0]",
+            "a[@b=c]",
+            "a[last()]",
+            "a/@b/c",
+            "a/@",
+            "a[1]x",
+            "count(a)",
+        ] {
+            assert!(validate_xpath(path).is_err(), "{}", path);
+        }
+        assert!(validate_xpath("//a[@b='c]']/d").is_ok());
+    }
+
+    #[test]
+    fn html_elements_are_skipped_by_css_selector() {
+        let html = r#"<html><head><script>var t = 1;</script></head>
+<body><div class="ad">ad</div><p id="x"  class="b a">hello   <b>world</b></p><br></body></html>"#;
+        let text = filter_html(html, &skip(&[".ad", "head script"])).unwrap();
+        assert!(
+            !text.contains("ad</div>") && !text.contains("var t"),
+            "{}",
+            text
+        );
+        assert!(text.contains("<p class=\"b a\" id=\"x\">"), "{}", text);
+        assert!(text.contains("  hello\n"), "{}", text);
+        assert!(text.contains("<br>\n"), "{}", text);
+        assert!(parse_selector("div[").is_err());
+    }
+
+    #[test]
+    fn yaml_keys_are_sorted_and_skipped() {
+        let yaml = filter_yaml(
+            "b: 1\na: {d: 1, c: 2}\nat: now\n---\nz: 1\n",
+            &skip(&["at"]),
+        )
+        .unwrap();
+        assert_eq!(yaml, "a:\n  c: 2\n  d: 1\nb: 1\n---\nz: 1\n");
+    }
+}
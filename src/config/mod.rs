@@ -1,20 +1,75 @@
+mod binary;
+mod body;
+mod collection;
+mod curl;
 mod diff;
+mod expect;
+mod export;
+mod format;
+mod graphql;
+mod grpc;
+mod har;
+mod markup;
+mod openapi;
+mod protobuf;
+mod redirect;
+mod repeat;
 mod req;
+mod retry;
+mod route;
+mod schema;
+mod sse;
+mod timing;
+mod tls;
+mod user_agent;
+mod websocket;
 
-use anyhow::{Ok, Result};
+use anyhow::{Context, Ok, Result};
 use async_trait::async_trait;
-use http::{header::CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue, Method};
+use body::{deserialize_body, encode_multipart, gen_boundary, serialize_body};
+use bytes::{Bytes, BytesMut};
+use encoding_rs::{Encoding, UTF_8};
+use http::{
+    header::{ACCEPT, CONTENT_TYPE, LOCATION, USER_AGENT},
+    HeaderMap, HeaderName, HeaderValue, Method,
+};
 use mime::Mime;
-use reqwest::{Client, Response};
+use redirect::{redirect_policy, RedirectChain};
+use reqwest::{Client, Request, Response};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use serde_json::{json, Value};
+use serde_json::Value;
 use std::fmt::Write;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use timing::ConnectionTimer;
 use tokio::fs;
 use url::Url;
 
-pub use diff::{DiffConfig, DiffProfile, ResponseProfile};
+pub use binary::{binary_summary, diff_binary, is_binary};
+pub use body::{MultipartPart, RequestBody};
+pub use collection::{parse_collection, CollectionVars};
+pub use curl::{parse_curl_args, parse_curl_commands};
+pub use diff::{DiffConfig, DiffProfile, DiffResult, ResponseProfile};
+pub use expect::{Expect, StatusExpect, StatusRange};
+pub use format::BodyFormat;
+pub use graphql::{split_graphql, GraphqlRequest, GraphqlText};
+pub use grpc::GrpcRequest;
+pub use har::{parse_har, retarget_url, HarFilter};
+pub use markup::{filter_html, filter_xml, filter_yaml};
+pub use openapi::parse_openapi;
+pub use protobuf::ProtobufMessage;
+pub use redirect::{FollowRedirects, RedirectHop};
+pub use repeat::{RepeatResult, Variance};
 pub use req::RequestConfig;
+pub use retry::{Backoff, RetryError, RetryOn, RetryProfile};
+pub use route::{parse_resolve, ResolveEntry, RouteProfile};
+pub use schema::{JsonSchema, SchemaError};
+pub use sse::{filter_sse, parse_sse, SseEvent, SseProfile};
+pub use timing::Timing;
+pub use tls::TlsProfile;
+pub use user_agent::{resolve_user_agent, USER_AGENT_PRESETS};
+pub use websocket::WebsocketProfile;
 
 #[async_trait]
 pub trait LoadConfig
@@ -28,10 +83,13 @@ where
     }
     /// load config from yaml string
     fn from_yaml(content: &str) -> Result<Self> {
-        let config: Self = serde_yaml::from_str(content)?;
+        let mut config: Self = serde_yaml::from_str(content)?;
+        config.apply_defaults();
         config.validate()?;
         Ok(config)
     }
+    /// apply the config-wide defaults to every profile
+    fn apply_defaults(&mut self) {}
 }
 
 pub trait ValidateConfig {
@@ -52,10 +110,54 @@ pub struct RequestProfile {
     #[serde(skip_serializing_if = "HeaderMap::is_empty", default)]
     #[serde(with = "http_serde::header_map")]
     pub headers: HeaderMap,
+    /// sent as json unless `body_type` is set
+    #[serde(
+        flatten,
+        serialize_with = "serialize_body",
+        deserialize_with = "deserialize_body"
+    )]
+    pub body: Option<RequestBody>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub graphql: Option<GraphqlRequest>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub user_agent: Option<String>,
+    #[serde(flatten)]
+    pub retry: RetryProfile,
+    #[serde(flatten)]
+    pub route: RouteProfile,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub follow_redirects: Option<FollowRedirects>,
+    #[serde(skip_serializing_if = "is_default", default)]
+    pub tls: TlsProfile,
+    #[serde(skip_serializing_if = "is_default", default)]
+    pub expect: Expect,
+    /// collect the events of a `text/event-stream` response
     #[serde(skip_serializing_if = "Option::is_none", default)]
-    pub body: Option<Value>,
+    pub sse: Option<SseProfile>,
+    /// talk to the url over a websocket instead of sending a request
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub websocket: Option<WebsocketProfile>,
+    /// call a unary rpc on the url instead of sending a request
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub grpc: Option<GrpcRequest>,
+    /// decode a protobuf response body as this message
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub protobuf: Option<ProtobufMessage>,
+}
+
+/// settings shared by every request of a config, overridden by each profile
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestDefaults {
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub user_agent: Option<String>,
+    #[serde(flatten)]
+    pub retry: RetryProfile,
+    #[serde(flatten)]
+    pub route: RouteProfile,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub follow_redirects: Option<FollowRedirects>,
+    #[serde(skip_serializing_if = "is_default", default)]
+    pub tls: TlsProfile,
 }
 
 pub fn is_default<T: Default + PartialEq>(t: &T) -> bool {
@@ -70,8 +172,23 @@ pub fn default_params() -> Value {
     serde_json::json!({})
 }
 
+/// what a response failed and how long it took, reported apart from the diff
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResponseReport {
+    /// expectations the response doesn't meet
+    pub failures: Vec<String>,
+    pub timing: Timing,
+}
+
 #[derive(Debug)]
-pub struct ResponseExt(Response);
+pub struct ResponseExt {
+    res: Response,
+    attempts: u32,
+    redirects: Vec<RedirectHop>,
+    timing: Timing,
+    sse: Option<SseProfile>,
+    protobuf: Option<ProtobufMessage>,
+}
 
 impl RequestProfile {
     pub fn new(
@@ -79,7 +196,7 @@ impl RequestProfile {
         url: Url,
         params: Value,
         headers: HeaderMap,
-        body: Option<Value>,
+        body: Option<RequestBody>,
         user_agent: Option<String>,
     ) -> Self {
         Self {
@@ -88,22 +205,136 @@ impl RequestProfile {
             params,
             headers,
             body,
+            graphql: None,
             user_agent,
+            retry: RetryProfile::default(),
+            route: RouteProfile::default(),
+            follow_redirects: None,
+            tls: TlsProfile::default(),
+            expect: Expect::default(),
+            sse: None,
+            websocket: None,
+            grpc: None,
+            protobuf: None,
         }
     }
+
+    pub fn apply_defaults(&mut self, defaults: &RequestDefaults) {
+        if self.grpc.is_some() || self.websocket.is_some() {
+            // a grpc call or websocket conversation is never retried, only the timeout applies
+            self.retry.timeout = self.retry.timeout.or(defaults.retry.timeout);
+        } else {
+            self.retry.merge(&defaults.retry);
+        }
+        if self.follow_redirects.is_none() {
+            self.follow_redirects = defaults.follow_redirects;
+        }
+        if self.user_agent.is_none() {
+            self.user_agent = defaults.user_agent.clone();
+        }
+        // websocket and grpc connections don't go through the http client these apply to
+        if self.websocket.is_none() && self.grpc.is_none() {
+            self.route.merge(&defaults.route);
+            self.tls.merge(&defaults.tls);
+        }
+    }
+
     pub async fn send(&self, extra_args: &super::ExtraArgs) -> Result<ResponseExt> {
-        let url = self.url.clone();
-        let (headers, body, query) = self.gen_req_config(extra_args)?;
-        let req_builder = Client::builder().build()?;
-        let res = req_builder
-            .request(self.method.clone(), url)
+        let mut url = self.url.clone();
+        let chain = RedirectChain::default();
+        let timer = ConnectionTimer::default();
+        let client = self
+            .build_client(&mut url, chain.clone(), timer.clone())
+            .await?;
+        let request = self.build_request(&client, url, extra_args).await?;
+        let max_attempts = self.retry.max_attempts();
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+            chain.lock().unwrap().clear();
+            timer.reset();
+            let attempt = request
+                .try_clone()
+                .ok_or_else(|| anyhow::anyhow!("request body can't be cloned"))?;
+            let start = Instant::now();
+            let result = client.execute(attempt).await;
+            let elapsed = start.elapsed();
+
+            let retry = attempts < max_attempts
+                && result.as_ref().map_or_else(
+                    |e| self.retry.should_retry_error(e),
+                    |res| self.retry.should_retry_status(res.status()),
+                );
+            if !retry {
+                let res = result.with_context(|| {
+                    format!("{} failed after {} attempt(s)", self.url, attempts)
+                })?;
+                let redirects = chain.lock().unwrap().drain(..).collect();
+                let timing = timer.timing(start, elapsed);
+                return Ok(ResponseExt {
+                    res,
+                    attempts,
+                    redirects,
+                    timing,
+                    sse: self.sse.clone(),
+                    protobuf: self.protobuf.clone(),
+                });
+            }
+            tokio::time::sleep(self.retry.delay(attempts)).await;
+        }
+    }
+
+    /// the request `send` would send, without the client settings
+    pub async fn prepare_request(&self, extra_args: &super::ExtraArgs) -> Result<Request> {
+        self.build_request(&Client::new(), self.url.clone(), extra_args)
+            .await
+    }
+
+    async fn build_request(
+        &self,
+        client: &Client,
+        url: Url,
+        extra_args: &super::ExtraArgs,
+    ) -> Result<Request> {
+        let (headers, body, query) = self.gen_req_config(extra_args).await?;
+        let mut builder = client
+            .request(self.request_method(), url)
             .headers(headers)
-            .query(&query)
-            .body(body)
-            .send()
+            .query(&query);
+        if let Some(body) = body {
+            builder = builder.body(body);
+        }
+        Ok(builder.build()?)
+    }
+
+    async fn build_client(
+        &self,
+        url: &mut Url,
+        chain: RedirectChain,
+        timer: ConnectionTimer,
+    ) -> Result<Client> {
+        let mut builder = Client::builder()
+            .redirect(redirect_policy(self.follow_redirects, chain))
+            .dns_resolver(Arc::new(timer.clone()));
+        if let Some(timeout) = self.retry.timeout() {
+            builder = builder.timeout(timeout);
+        }
+        let builder = self.route.configure(builder, url)?;
+        let builder = self
+            .tls
+            .configure(builder, url, &self.route, &timer)
             .await?;
+        Ok(builder.build()?)
+    }
 
-        Ok(ResponseExt(res))
+    /// graphql requests are sent as POST unless another method is set
+    pub fn request_method(&self) -> Method {
+        if self.graphql.is_some() && self.method == Method::GET {
+            Method::POST
+        } else {
+            self.method.clone()
+        }
     }
 
     pub(crate) fn validate(&self) -> Result<()> {
@@ -114,12 +345,71 @@ impl RequestProfile {
             ));
         }
         if let Some(body) = self.body.as_ref() {
-            if !body.is_object() {
+            body.validate()?;
+        }
+        if let Some(graphql) = self.graphql.as_ref() {
+            if self.body.is_some() {
+                return Err(anyhow::anyhow!("graphql 和 body 不能同时设置"));
+            }
+            graphql.validate()?;
+        }
+        self.route.validate()?;
+        self.tls.validate()?;
+        self.expect.validate()?;
+        if let Some(sse) = &self.sse {
+            sse.validate()?;
+        }
+        if let Some(websocket) = &self.websocket {
+            if self.body.is_some() || self.graphql.is_some() || self.sse.is_some() {
+                return Err(anyhow::anyhow!(
+                    "websocket 不能和 body、graphql 或 sse 同时设置"
+                ));
+            }
+            if !is_default(&self.tls) || !is_default(&self.route) {
+                return Err(anyhow::anyhow!(
+                    "websocket 不支持 tls、proxy 和 resolve 设置"
+                ));
+            }
+            let retry = &self.retry;
+            if retry.retries.is_some() || retry.retry_on.is_some() || retry.backoff.is_some() {
+                return Err(anyhow::anyhow!(
+                    "websocket 只支持 timeout，不支持 retries、retry_on 和 backoff"
+                ));
+            }
+            if !is_default(&self.expect) {
+                return Err(anyhow::anyhow!("websocket 不支持 expect"));
+            }
+            websocket.validate()?;
+        }
+        if let Some(grpc) = &self.grpc {
+            if self.body.is_some()
+                || self.graphql.is_some()
+                || self.sse.is_some()
+                || self.websocket.is_some()
+            {
                 return Err(anyhow::anyhow!(
-                    "body:{} 不是一个对象",
-                    serde_yaml::to_string(body)?
+                    "grpc 不能和 body、graphql、sse 或 websocket 同时设置"
                 ));
             }
+            if !is_default(&self.tls) || !is_default(&self.route) {
+                return Err(anyhow::anyhow!("grpc 不支持 tls、proxy 和 resolve 设置"));
+            }
+            let retry = &self.retry;
+            if retry.retries.is_some() || retry.retry_on.is_some() || retry.backoff.is_some() {
+                return Err(anyhow::anyhow!(
+                    "grpc 只支持 timeout，不支持 retries、retry_on 和 backoff"
+                ));
+            }
+            if !is_empty_value(&self.params) {
+                return Err(anyhow::anyhow!("grpc 不支持 params"));
+            }
+            if !is_default(&self.expect) {
+                return Err(anyhow::anyhow!("grpc 不支持 expect"));
+            }
+            grpc.validate()?;
+        }
+        if let Some(protobuf) = &self.protobuf {
+            protobuf.validate()?;
         }
         Ok(())
     }
@@ -132,44 +422,109 @@ impl RequestProfile {
         Ok(url.to_string())
     }
 
-    pub fn gen_req_config(
+    pub async fn gen_req_config(
         &self,
         extra_args: &super::ExtraArgs,
-    ) -> Result<(HeaderMap, String, Value)> {
+    ) -> Result<(HeaderMap, Option<Vec<u8>>, Value)> {
         let mut headers = self.headers.clone();
-        let mut body = self.body.clone().unwrap_or_else(|| json!({}));
+        // a profile without a body sends none unless the extra args add fields or files
+        let body = match &self.graphql {
+            Some(graphql) => Some(RequestBody::Json(graphql.to_json(&extra_args.body).await?)),
+            None if extra_args.body.is_empty() && extra_args.files.is_empty() => self.body.clone(),
+            None => Some(self.body.clone().unwrap_or_default()),
+        };
         let mut query = self.params.clone();
 
-        if !headers.contains_key(CONTENT_TYPE) {
+        if let Some(body) = body
+            .as_ref()
+            .filter(|_| !headers.contains_key(CONTENT_TYPE))
+        {
             headers.insert(
                 CONTENT_TYPE,
-                HeaderValue::from_str(&mime::APPLICATION_JSON.to_string())?,
+                HeaderValue::from_str(body.default_content_type().as_ref())?,
+            );
+        }
+
+        if self.sse.is_some() && !headers.contains_key(ACCEPT) {
+            headers.insert(ACCEPT, HeaderValue::from_static("text/event-stream"));
+        }
+
+        if let Some(user_agent) = &self.user_agent {
+            headers.insert(
+                USER_AGENT,
+                HeaderValue::from_str(resolve_user_agent(user_agent))?,
             );
         }
 
         for (k, v) in &extra_args.headers {
             headers.insert(HeaderName::from_str(k)?, HeaderValue::from_str(v)?);
         }
-        for (k, v) in &extra_args.body {
-            body[k] = v.parse()?;
+        if let Some(user_agent) = &extra_args.user_agent {
+            headers.insert(
+                USER_AGENT,
+                HeaderValue::from_str(resolve_user_agent(user_agent))?,
+            );
         }
+        if !extra_args.files.is_empty() {
+            headers.insert(
+                CONTENT_TYPE,
+                HeaderValue::from_str(mime::MULTIPART_FORM_DATA.as_ref())?,
+            );
+        }
+
         for (k, v) in &extra_args.query {
             query[k] = v.parse()?;
         }
 
+        let Some(mut body) = body else {
+            return Ok((headers, None, query));
+        };
         let content_type = get_content_type(&headers);
 
+        if content_type.as_ref() == Some(&mime::MULTIPART_FORM_DATA) {
+            let mut parts = body.to_multipart()?;
+            for (k, v) in &extra_args.body {
+                parts.retain(|p| &p.name != k);
+                parts.push(MultipartPart::text(k, v));
+            }
+            for (k, path) in &extra_args.files {
+                parts.push(MultipartPart::file(k, path));
+            }
+            let boundary = gen_boundary();
+            headers.insert(
+                CONTENT_TYPE,
+                HeaderValue::from_str(&format!("multipart/form-data; boundary={}", boundary))?,
+            );
+            let body = encode_multipart(&parts, &boundary).await?;
+            return Ok((headers, Some(body), query));
+        }
+
+        // graphql requests already took the extra body args as variables
+        if !extra_args.body.is_empty() && self.graphql.is_none() {
+            let fields = body.fields_mut().filter(|v| v.is_object()).ok_or_else(|| {
+                anyhow::anyhow!("extra body args 只能用于对象类型的 json/form body")
+            })?;
+            for (k, v) in &extra_args.body {
+                fields[k] = v.parse()?;
+            }
+        }
+
+        if let Some(bytes) = body.raw_bytes().await? {
+            return Ok((headers, Some(bytes), query));
+        }
+
+        let fields = body.fields_mut().ok_or_else(|| {
+            anyhow::anyhow!("multipart body 需要 multipart/form-data CONTENT_TYPE")
+        })?;
+
         match content_type {
-            Some(content) if content == mime::APPLICATION_JSON => {
-                let body = serde_json::to_string(&body)?;
-                Ok((headers, body, query))
+            Some(content) if BodyFormat::from_mime(&content) == Some(BodyFormat::Json) => {
+                let body = serde_json::to_vec(fields)?;
+                Ok((headers, Some(body), query))
             }
-            Some(content)
-                if content == mime::APPLICATION_WWW_FORM_URLENCODED
-                    || content == mime::MULTIPART_FORM_DATA =>
-            {
-                let body = serde_qs::to_string(&body)?;
-                Ok((headers, body, query))
+            Some(content) if content == mime::APPLICATION_WWW_FORM_URLENCODED => {
+                let body = serde_qs::to_string(fields)?.into_bytes();
+                Ok((headers, Some(body), query))
             }
             _ => Err(anyhow::anyhow!("不是有效的 CONTENT_TYPE")),
         }
@@ -178,49 +533,131 @@ impl RequestProfile {
 
 impl FromStr for RequestProfile {
     type Err = anyhow::Error;
+    /// a url or a curl command line
     fn from_str(s: &str) -> Result<Self> {
-        let mut url = Url::parse(s)?;
-        let qs = Url::query_pairs(&url);
-        let mut parmas = json!({});
-        for (k, v) in qs {
-            parmas[&*k] = v.parse()?;
-        }
-        url.set_query(None);
-        let profile = Self {
-            method: Method::GET,
-            url,
-            params: parmas,
-            headers: HeaderMap::new(),
-            body: None,
-            user_agent: None,
-        };
-        Ok(profile)
+        Self::from_curl(s)
     }
 }
+
 impl ResponseExt {
     pub fn into_inner(self) -> Response {
-        self.0
+        self.res
     }
-    pub async fn filter_text(self, profile: &ResponseProfile) -> Result<String> {
-        let res = self.into_inner();
-        let mut output = get_status_text(&res)?;
-
-        write!(
-            &mut output,
-            "{}",
-            get_header_text(&res, &profile.skip_headers)?
-        )?;
+    pub fn response(&self) -> &Response {
+        &self.res
+    }
+    /// the body filtered like `get_body_text`, read by the `sse` and `protobuf` of the request
+    pub async fn body_text(self) -> Result<String> {
+        let content_type = get_content_type(self.res.headers());
+        let profile = ResponseProfile::default();
+        let read = BodyRead {
+            limit: None,
+            sse: self.sse.as_ref(),
+            protobuf: self.protobuf.as_ref(),
+        };
+        match Body::read(self.res, read).await?.0 {
+            Body::Text(text) => filter_body(content_type, text, &profile),
+            Body::Binary(bytes) => binary_summary(&bytes),
+        }
+    }
+    /// number of attempts it took to get this response
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+    /// redirects followed before the final response
+    pub fn redirects(&self) -> &[RedirectHop] {
+        &self.redirects
+    }
+    /// time from sending the last attempt to the response headers
+    pub fn elapsed(&self) -> Duration {
+        self.timing.ttfb
+    }
+    /// the text to diff, the bytes of a binary body and what the response doesn't meet of
+    /// `expects`
+    pub async fn filter_text(
+        self,
+        profile: &ResponseProfile,
+        expects: &[&Expect],
+    ) -> Result<(String, Option<Bytes>, ResponseReport)> {
+        let (mut output, content_type, body, report) = self.read(profile, expects).await?;
+        let bytes = match body {
+            Body::Text(text) => {
+                write!(&mut output, "{}", filter_body(content_type, text, profile)?)?;
+                None
+            }
+            Body::Binary(bytes) => {
+                write!(&mut output, "{}", binary_summary(&bytes)?)?;
+                Some(bytes)
+            }
+        };
+        Ok((output, bytes, report))
+    }
+    /// redirects, status and headers followed by the graphql `data` and `errors` of the body
+    pub async fn filter_graphql(
+        self,
+        profile: &ResponseProfile,
+        expects: &[&Expect],
+    ) -> Result<(String, GraphqlText, ResponseReport)> {
+        let (head, _, body, report) = self.read(profile, expects).await?;
+        let text = match body {
+            Body::Text(text) => text,
+            Body::Binary(_) => anyhow::bail!("graphql response body is binary"),
+        };
+        Ok((head, split_graphql(&text, &profile.skip_body)?, report))
+    }
+    /// head text, content type and body, with the expectations checked on the way
+    async fn read(
+        self,
+        profile: &ResponseProfile,
+        expects: &[&Expect],
+    ) -> Result<(String, Option<Mime>, Body, ResponseReport)> {
+        let mut report = ResponseReport {
+            failures: expects.iter().flat_map(|e| self.check(e)).collect(),
+            timing: self.timing,
+        };
+        let mut head = self.head_text(profile)?;
+        let content_type = get_content_type(self.res.headers());
+        let read = BodyRead {
+            limit: profile.max_body_size,
+            sse: self.sse.as_ref(),
+            protobuf: self.protobuf.as_ref(),
+        };
+        let start = Instant::now();
+        let (body, size, truncated) = Body::read(self.res, read).await?;
+        report.timing.total = report.timing.ttfb + start.elapsed();
+        report.timing.size = size;
+        if let (true, Some(limit)) = (truncated, profile.max_body_size) {
+            writeln!(&mut head, "body truncated to {} bytes", limit)?;
+        }
+        let text = body.text();
+        for expect in expects {
+            report.failures.extend(expect.check_body(&text, truncated)?);
+        }
+        Ok((head, content_type, body, report))
+    }
+    fn head_text(&self, profile: &ResponseProfile) -> Result<String> {
+        let mut output = get_redirect_text(&self.redirects)?;
+        let res = &self.res;
+        write!(&mut output, "{}", get_status_text(res)?)?;
+        // a redirect that was not followed, show where it points to
+        if let Some(location) = res
+            .status()
+            .is_redirection()
+            .then(|| res.headers().get(LOCATION))
+            .flatten()
+        {
+            writeln!(&mut output, "location: {:?}", location)?;
+        }
 
         write!(
             &mut output,
             "{}",
-            get_body_text(res, &profile.skip_body).await?
+            get_header_text(res, &profile.skip_headers)?
         )?;
-
         Ok(output)
     }
     pub fn get_header_keys(self) -> Vec<String> {
-        let res = self.0;
+        let res = self.res;
         let headers = res.headers();
         headers
             .iter()
@@ -233,26 +670,139 @@ pub fn get_status_text(res: &Response) -> Result<String> {
     Ok(format!("{:?} {:?} \n", res.version(), res.status()))
 }
 
+pub fn get_redirect_text(redirects: &[RedirectHop]) -> Result<String> {
+    let mut output = String::new();
+    for hop in redirects {
+        writeln!(&mut output, "redirect {:?} -> {}", hop.status, hop.location)?;
+    }
+    Ok(output)
+}
+
 pub fn get_header_text(res: &Response, skip_headers: &[String]) -> Result<String> {
+    Ok(header_map_text(res.headers(), skip_headers))
+}
+
+fn header_map_text(headers: &HeaderMap, skip_headers: &[String]) -> String {
     let mut output = String::new();
-    let headers = res.headers();
     headers.iter().for_each(|(k, v)| {
         if skip_headers.contains(&k.to_string()) {
             output.push_str(&format!("{}: {:?} \n", k, v));
         }
     });
-    Ok(output)
+    output
 }
 
 pub async fn get_body_text(res: Response, skip_headers: &[String]) -> Result<String> {
     let headers = res.headers();
-    let content_type = get_content_type(&headers);
-    let text = res.text().await?;
-    match content_type {
-        Some(content) if content == mime::APPLICATION_JSON => {
-            let body_text = filter_json(&text, &skip_headers)?;
-            Ok(body_text)
+    let content_type = get_content_type(headers);
+    let profile = ResponseProfile::new(vec![], skip_headers.to_vec());
+    match Body::read(res, BodyRead::default()).await?.0 {
+        Body::Text(text) => filter_body(content_type, text, &profile),
+        Body::Binary(bytes) => binary_summary(&bytes),
+    }
+}
+
+/// how a response body is read
+#[derive(Default)]
+struct BodyRead<'a> {
+    /// bytes read before the rest is dropped
+    limit: Option<u64>,
+    sse: Option<&'a SseProfile>,
+    protobuf: Option<&'a ProtobufMessage>,
+}
+
+/// a response body, decoded by its charset unless it's binary
+enum Body {
+    Text(String),
+    Binary(Bytes),
+}
+
+impl Body {
+    /// streamed up to `limit` bytes, the bytes read and whether the rest was dropped come along,
+    /// an event stream is read as the events `sse` collects, a whole protobuf body as json
+    async fn read(mut res: Response, read: BodyRead<'_>) -> Result<(Self, u64, bool)> {
+        let mime = res
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<Mime>().ok());
+        if mime.as_ref().map(|m| m.essence_str()) == Some(mime::TEXT_EVENT_STREAM.as_ref()) {
+            let (events, size, truncated) = read
+                .sse
+                .cloned()
+                .unwrap_or_default()
+                .collect(&mut res, read.limit)
+                .await?;
+            let text: String = events.iter().map(|e| e.to_wire()).collect();
+            return Ok((Self::Text(text), size, truncated));
+        }
+        let (bytes, truncated) = match read.limit {
+            None => (res.bytes().await?, false),
+            Some(limit) => {
+                let mut buf = BytesMut::new();
+                let mut truncated = false;
+                while let Some(chunk) = res.chunk().await? {
+                    let room = (limit as usize).saturating_sub(buf.len());
+                    if chunk.len() > room {
+                        buf.extend_from_slice(&chunk[..room]);
+                        truncated = true;
+                        break;
+                    }
+                    buf.extend_from_slice(&chunk);
+                }
+                (buf.freeze(), truncated)
+            }
+        };
+        let size = bytes.len() as u64;
+        // a cut off message doesn't decode, it's shown like any binary body
+        if let Some(protobuf) = read.protobuf {
+            if !truncated && ProtobufMessage::accepts(mime.as_ref()) {
+                return Ok((Self::Text(protobuf.decode_json(&bytes)?), size, truncated));
+            }
+        }
+        if is_binary(mime.as_ref(), &bytes) {
+            return Ok((Self::Binary(bytes), size, truncated));
         }
+        let encoding = mime
+            .as_ref()
+            .and_then(|m| m.get_param(mime::CHARSET))
+            .and_then(|charset| Encoding::for_label(charset.as_str().as_bytes()))
+            .unwrap_or(UTF_8);
+        let (text, _, _) = encoding.decode(&bytes);
+        let mut text = text.into_owned();
+        // keep whole lines, so the records of a cut off ndjson body still parse
+        if let (true, Some(end)) = (truncated, text.rfind('\n')) {
+            text.truncate(end + 1);
+        }
+        Ok((Self::Text(text), size, truncated))
+    }
+
+    /// what expectations and the schema look at
+    fn text(&self) -> String {
+        match self {
+            Self::Text(text) => text.clone(),
+            Self::Binary(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+        }
+    }
+}
+
+/// the declared format first, then a sniffed one, then the raw text, unless `body_format` is set
+fn filter_body(
+    content_type: Option<Mime>,
+    text: String,
+    profile: &ResponseProfile,
+) -> Result<String> {
+    if let Some(format) = profile.body_format {
+        return format.filter(&text, profile);
+    }
+    let declared = content_type.as_ref().and_then(BodyFormat::from_mime);
+    if let Some(format) = declared {
+        if let Result::Ok(body) = format.filter(&text, profile) {
+            return Ok(body);
+        }
+    }
+    match BodyFormat::sniff(&text) {
+        format if Some(format) != declared => Ok(format.filter(&text, profile).unwrap_or(text)),
         _ => Ok(text),
     }
 }
@@ -267,9 +817,96 @@ pub fn filter_json(text: &str, skip: &[String]) -> Result<String> {
     Ok(serde_json::to_string_pretty(&json)?)
 }
 
+/// every record of newline delimited json filtered like `filter_json`, `---` between records
+pub fn filter_ndjson(text: &str, skip: &[String]) -> Result<String> {
+    let records = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(i, line)| {
+            filter_json(line, skip)
+                .with_context(|| format!("ndjson record {} is not valid json", i + 1))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(records.join("\n---\n"))
+}
+
 pub fn get_content_type(headers: &HeaderMap) -> Option<Mime> {
     headers
         .get(http::header::CONTENT_TYPE)
         .and_then(|v| v.to_str().unwrap().split(";").next())
         .and_then(|s| s.parse::<mime::Mime>().ok())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(content_type: &str, body: &'static str) -> Response {
+        http::Response::builder()
+            .header(CONTENT_TYPE, content_type)
+            .body(body)
+            .unwrap()
+            .into()
+    }
+
+    fn skip_body(keys: &[&str]) -> ResponseProfile {
+        ResponseProfile {
+            skip_body: keys.iter().map(|k| k.to_string()).collect(),
+            ..ResponseProfile::default()
+        }
+    }
+
+    #[test]
+    fn ndjson_records_are_filtered_one_by_one() {
+        let text = "{\"id\": 1, \"at\": 5}\n\n{\"id\": 2, \"at\": 6}\r\n";
+        assert_eq!(
+            filter_ndjson(text, &["at".to_string()]).unwrap(),
+            "{\n  \"id\": 1\n}\n---\n{\n  \"id\": 2\n}"
+        );
+        let err = filter_ndjson("{\"id\": 1}\n\n{\"id\":", &[]).unwrap_err();
+        assert_eq!(err.to_string(), "ndjson record 2 is not valid json");
+    }
+
+    #[test]
+    fn ndjson_is_picked_by_content_type_or_sniffed() {
+        let text = "{\"id\": 1, \"at\": 5}\n{\"id\": 2, \"at\": 6}\n".to_string();
+        let expected = "{\n  \"id\": 1\n}\n---\n{\n  \"id\": 2\n}";
+        let declared = Some("application/x-ndjson".parse().unwrap());
+        assert_eq!(
+            filter_body(declared, text.clone(), &skip_body(&["at"])).unwrap(),
+            expected
+        );
+        // declared as json, the sniffed format is used when it doesn't parse as one
+        let declared = Some(mime::APPLICATION_JSON);
+        assert_eq!(
+            filter_body(declared, text, &skip_body(&["at"])).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn a_body_that_fails_every_format_is_kept_as_text() {
+        let declared = Some("application/x-ndjson".parse().unwrap());
+        let text = "{\"id\": 1}\nnot json\n".to_string();
+        assert_eq!(
+            filter_body(declared, text.clone(), &ResponseProfile::default()).unwrap(),
+            text
+        );
+    }
+
+    #[tokio::test]
+    async fn a_truncated_ndjson_body_keeps_whole_records() {
+        let res = response(
+            "application/x-ndjson",
+            "{\"id\": 1}\n{\"id\": 2}\n{\"id\": 3}\n",
+        );
+        let read = BodyRead {
+            limit: Some(15),
+            ..BodyRead::default()
+        };
+        let (body, size, truncated) = Body::read(res, read).await.unwrap();
+        assert_eq!((size, truncated), (15, true));
+        assert_eq!(body.text(), "{\"id\": 1}\n");
+    }
+}
@@ -0,0 +1,475 @@
+use std::collections::HashSet;
+
+use anyhow::{anyhow, Context, Result};
+use http::{header::CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue, Method};
+use serde_json::{json, Map, Value};
+use url::Url;
+
+use super::{
+    body::value_to_text,
+    har::{profile_name, unique_name},
+    DiffConfig, DiffProfile, MultipartPart, RequestBody, RequestConfig, RequestProfile,
+    ResponseProfile,
+};
+
+const METHODS: &[&str] = &[
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+struct Spec<'a> {
+    root: &'a Value,
+}
+
+/// one profile per operation of an OpenAPI 3 document (yaml or json), sent to `base` or the first server
+pub fn parse_openapi(text: &str, base: Option<&Url>) -> Result<Vec<(String, RequestProfile)>> {
+    let root: Value = serde_yaml::from_str(text).context("不是有效的 openapi 文档")?;
+    match root.get("openapi").and_then(|v| v.as_str()) {
+        Some(version) if version.starts_with('3') => {}
+        _ => return Err(anyhow!("只支持 OpenAPI 3 文档")),
+    }
+    let spec = Spec { root: &root };
+    let base = match base {
+        Some(base) => base.clone(),
+        None => spec.server_url()?,
+    };
+
+    let mut names = HashSet::new();
+    let mut profiles = vec![];
+    let Some(paths) = root.get("paths").and_then(|p| p.as_object()) else {
+        return Ok(profiles);
+    };
+    for (path, item) in paths {
+        let item = spec.resolve(item)?;
+        for method in METHODS {
+            let Some(op) = item.get(*method) else {
+                continue;
+            };
+            let method = Method::from_bytes(method.to_uppercase().as_bytes())?;
+            let profile = spec
+                .operation_to_profile(&base, path, &method, item, op)
+                .with_context(|| format!("openapi operation: {} {}", method, path))?;
+
+            let name = match op.get("operationId").and_then(|v| v.as_str()) {
+                Some(id) => id.to_string(),
+                None => profile_name(&method, path),
+            };
+            profiles.push((unique_name(&mut names, name), profile));
+        }
+    }
+    Ok(profiles)
+}
+
+impl<'a> Spec<'a> {
+    /// follow local `$ref`s such as `#/components/schemas/User`
+    fn resolve(&self, mut value: &'a Value) -> Result<&'a Value> {
+        for _ in 0..32 {
+            let Some(reference) = value.get("$ref").and_then(|r| r.as_str()) else {
+                return Ok(value);
+            };
+            let pointer = reference
+                .strip_prefix('#')
+                .ok_or_else(|| anyhow!("只支持文档内的 $ref: {}", reference))?;
+            value = self
+                .root
+                .pointer(pointer)
+                .ok_or_else(|| anyhow!("$ref 不存在: {}", reference))?;
+        }
+        Err(anyhow!("$ref 嵌套过深"))
+    }
+
+    /// the first server with its variables set to their defaults
+    fn server_url(&self) -> Result<Url> {
+        let server = self
+            .root
+            .pointer("/servers/0")
+            .ok_or_else(|| anyhow!("openapi 文档没有 servers，请指定 base url"))?;
+        let mut url = server
+            .get("url")
+            .and_then(|u| u.as_str())
+            .unwrap_or_default()
+            .to_string();
+        if let Some(vars) = server.get("variables").and_then(|v| v.as_object()) {
+            for (name, var) in vars {
+                let default = var.get("default").and_then(|d| d.as_str()).unwrap_or("");
+                url = url.replace(&format!("{{{}}}", name), default);
+            }
+        }
+        Url::parse(&url)
+            .with_context(|| format!("server url {} 不是绝对地址，请指定 base url", url))
+    }
+
+    fn operation_to_profile(
+        &self,
+        base: &Url,
+        path: &str,
+        method: &Method,
+        item: &Value,
+        op: &Value,
+    ) -> Result<RequestProfile> {
+        // operation parameters override the path item ones with the same name and location
+        let mut parameters: Vec<&Value> = vec![];
+        for param in [item, op]
+            .iter()
+            .filter_map(|v| v.get("parameters").and_then(|p| p.as_array()))
+            .flatten()
+        {
+            let param = self.resolve(param)?;
+            parameters
+                .retain(|p| p.get("name") != param.get("name") || p.get("in") != param.get("in"));
+            parameters.push(param);
+        }
+
+        let mut path = path.to_string();
+        let mut params = Map::new();
+        let mut headers = HeaderMap::new();
+        for param in parameters {
+            let name = param
+                .get("name")
+                .and_then(|n| n.as_str())
+                .unwrap_or_default();
+            let required = param
+                .get("required")
+                .and_then(|r| r.as_bool())
+                .unwrap_or(false);
+            let explicit = self.explicit_example(param)?;
+            match param.get("in").and_then(|i| i.as_str()) {
+                Some("path") => {
+                    let value = self.param_example(param, explicit)?;
+                    path = path.replace(&format!("{{{}}}", name), &value_to_text(&value));
+                }
+                // optional parameters are only sent when the document gives a value
+                Some("query") if required || explicit.is_some() => {
+                    let value = self.param_example(param, explicit)?;
+                    insert_query(&mut params, name, param, value);
+                }
+                Some("header") if required || explicit.is_some() => {
+                    let value = self.param_example(param, explicit)?;
+                    headers.insert(
+                        HeaderName::from_bytes(name.as_bytes())?,
+                        HeaderValue::from_str(&value_to_text(&value))?,
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        let mut url = base.clone();
+        url.set_path(&format!("{}{}", base.path().trim_end_matches('/'), path));
+
+        let body = match op.get("requestBody") {
+            Some(body) => self.request_body(self.resolve(body)?, &mut headers)?,
+            None => None,
+        };
+
+        Ok(RequestProfile::new(
+            method.clone(),
+            url,
+            Value::Object(params),
+            headers,
+            body,
+            None,
+        ))
+    }
+
+    /// the body of the first media type netdiff can send
+    fn request_body(&self, body: &Value, headers: &mut HeaderMap) -> Result<Option<RequestBody>> {
+        let Some(content) = body.get("content").and_then(|c| c.as_object()) else {
+            return Ok(None);
+        };
+        let pick = |pred: &dyn Fn(&str) -> bool| content.iter().find(|(k, _)| pred(k));
+        let media = pick(&|k| k == "application/json")
+            .or_else(|| pick(&|k| k.contains("json")))
+            .or_else(|| pick(&|k| k == "application/x-www-form-urlencoded"))
+            .or_else(|| pick(&|k| k == "multipart/form-data"))
+            .or_else(|| pick(&|k| k.starts_with("text/") || k.contains("xml")));
+        let Some((content_type, media)) = media else {
+            return Ok(None);
+        };
+
+        let example = match self.explicit_example(media)? {
+            Some(example) => example,
+            None => match media.get("schema") {
+                Some(schema) => self.schema_example(schema, &mut vec![])?,
+                None => Value::Null,
+            },
+        };
+        let body = match content_type.as_str() {
+            "application/json" => RequestBody::Json(example),
+            "application/x-www-form-urlencoded" => RequestBody::Form(example),
+            "multipart/form-data" => RequestBody::Multipart(
+                example
+                    .as_object()
+                    .map(|obj| {
+                        obj.iter()
+                            .map(|(k, v)| MultipartPart::text(k, &value_to_text(v)))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            ),
+            content_type => {
+                headers.insert(CONTENT_TYPE, HeaderValue::from_str(content_type)?);
+                if content_type.contains("json") {
+                    RequestBody::Text(serde_json::to_string_pretty(&example)?)
+                } else if content_type.contains("xml") {
+                    RequestBody::Xml(value_to_text(&example))
+                } else {
+                    RequestBody::Text(value_to_text(&example))
+                }
+            }
+        };
+        Ok(Some(body))
+    }
+
+    /// `example`, or the value of the first of `examples`
+    fn explicit_example(&self, value: &Value) -> Result<Option<Value>> {
+        if let Some(example) = value.get("example") {
+            return Ok(Some(example.clone()));
+        }
+        match value
+            .get("examples")
+            .and_then(|e| e.as_object())
+            .and_then(|e| e.values().next())
+        {
+            Some(example) => Ok(self.resolve(example)?.get("value").cloned()),
+            None => Ok(None),
+        }
+    }
+
+    fn param_example(&self, param: &Value, explicit: Option<Value>) -> Result<Value> {
+        match (explicit, param.get("schema")) {
+            (Some(example), _) => Ok(example),
+            (None, Some(schema)) => self.schema_example(schema, &mut vec![]),
+            (None, None) => Ok(json!("string")),
+        }
+    }
+
+    /// a value of the schema, `refs` holds the `$ref`s being expanded to stop at recursion
+    fn schema_example(&self, schema: &Value, refs: &mut Vec<String>) -> Result<Value> {
+        let reference = schema.get("$ref").and_then(|r| r.as_str());
+        if let Some(reference) = reference {
+            if refs.iter().any(|r| r == reference) {
+                return Ok(Value::Null);
+            }
+            refs.push(reference.to_string());
+        }
+        let value = self.build_example(self.resolve(schema)?, refs);
+        if reference.is_some() {
+            refs.pop();
+        }
+        value
+    }
+
+    fn is_recursive(schema: &Value, refs: &[String]) -> bool {
+        schema
+            .get("$ref")
+            .and_then(|r| r.as_str())
+            .is_some_and(|reference| refs.iter().any(|r| r == reference))
+    }
+
+    /// from the example, default or enum of the schema, or built from its type
+    fn build_example(&self, schema: &Value, refs: &mut Vec<String>) -> Result<Value> {
+        for key in ["example", "default"] {
+            if let Some(v) = schema.get(key) {
+                return Ok(v.clone());
+            }
+        }
+        if let Some(v) = schema
+            .get("enum")
+            .and_then(|e| e.as_array())
+            .and_then(|e| e.first())
+        {
+            return Ok(v.clone());
+        }
+        if let Some(all) = schema.get("allOf").and_then(|a| a.as_array()) {
+            let mut merged = Map::new();
+            for s in all {
+                if let Value::Object(obj) = self.schema_example(s, refs)? {
+                    merged.extend(obj);
+                }
+            }
+            return Ok(Value::Object(merged));
+        }
+        for key in ["oneOf", "anyOf"] {
+            if let Some(s) = schema
+                .get(key)
+                .and_then(|a| a.as_array())
+                .and_then(|a| a.first())
+            {
+                return self.schema_example(s, refs);
+            }
+        }
+
+        let ty = match schema.get("type") {
+            Some(Value::String(ty)) => ty.as_str(),
+            // 3.1 allows a list of types
+            Some(Value::Array(types)) => types
+                .iter()
+                .filter_map(|t| t.as_str())
+                .find(|t| *t != "null")
+                .unwrap_or("null"),
+            _ if schema.get("properties").is_some() => "object",
+            _ => "string",
+        };
+        let format = schema
+            .get("format")
+            .and_then(|f| f.as_str())
+            .unwrap_or_default();
+        Ok(match ty {
+            "object" => {
+                let mut obj = Map::new();
+                if let Some(props) = schema.get("properties").and_then(|p| p.as_object()) {
+                    // a property pointing back at an enclosing schema is left out
+                    for (k, v) in props {
+                        if !Self::is_recursive(v, refs) {
+                            obj.insert(k.clone(), self.schema_example(v, refs)?);
+                        }
+                    }
+                }
+                Value::Object(obj)
+            }
+            "array" => match schema.get("items") {
+                Some(items) if !Self::is_recursive(items, refs) => {
+                    json!([self.schema_example(items, refs)?])
+                }
+                _ => json!([]),
+            },
+            "integer" => schema.get("minimum").cloned().unwrap_or(json!(0)),
+            "number" => schema.get("minimum").cloned().unwrap_or(json!(0.0)),
+            "boolean" => json!(true),
+            "null" => Value::Null,
+            _ => json!(match format {
+                "date-time" => "2024-01-01T00:00:00Z",
+                "date" => "2024-01-01",
+                "uuid" => "00000000-0000-0000-0000-000000000000",
+                "email" => "user@example.com",
+                "uri" | "url" => "https://example.com",
+                _ => "string",
+            }),
+        })
+    }
+}
+
+/// add a query parameter as strings, arrays are joined with `,` since the params can't repeat
+/// a key, objects follow the parameter style: `name[key]` for `deepObject`, `name=key,value`
+/// without `explode`, and one parameter per property otherwise
+fn insert_query(params: &mut Map<String, Value>, name: &str, param: &Value, value: Value) {
+    let style = param
+        .get("style")
+        .and_then(|s| s.as_str())
+        .unwrap_or("form");
+    let explode = param
+        .get("explode")
+        .and_then(|e| e.as_bool())
+        .unwrap_or(style == "form");
+    let join = |items: Vec<String>| json!(items.join(","));
+    match value {
+        Value::Array(items) => {
+            params.insert(
+                name.to_string(),
+                join(items.iter().map(value_to_text).collect()),
+            );
+        }
+        Value::Object(obj) if style == "deepObject" => {
+            for (k, v) in obj {
+                params.insert(format!("{}[{}]", name, k), json!(value_to_text(&v)));
+            }
+        }
+        Value::Object(obj) if !explode => {
+            let items = obj
+                .iter()
+                .flat_map(|(k, v)| [k.clone(), value_to_text(v)])
+                .collect();
+            params.insert(name.to_string(), join(items));
+        }
+        Value::Object(obj) => {
+            for (k, v) in obj {
+                params.insert(k, json!(value_to_text(&v)));
+            }
+        }
+        value => {
+            params.insert(name.to_string(), value);
+        }
+    }
+}
+
+impl RequestConfig {
+    /// build a config from an OpenAPI 3 document
+    pub fn from_openapi(text: &str, base: Option<&Url>) -> Result<Self> {
+        Ok(Self::new(parse_openapi(text, base)?.into_iter().collect()))
+    }
+}
+
+impl DiffConfig {
+    /// build a config from an OpenAPI 3 document, req1 is sent to `base` and req2 to `target`,
+    /// without a `target` both sides go to the same server until req2 is edited
+    pub fn from_openapi(text: &str, base: Option<&Url>, target: Option<&Url>) -> Result<Self> {
+        let req1 = parse_openapi(text, base)?;
+        let req2 = match target {
+            Some(target) => parse_openapi(text, Some(target))?,
+            None => req1.clone(),
+        };
+        let profiles = req1
+            .into_iter()
+            .zip(req2)
+            .map(|((name, req1), (_, req2))| {
+                (
+                    name,
+                    DiffProfile::new(req1, req2, ResponseProfile::default()),
+                )
+            })
+            .collect();
+        Ok(Self::new(profiles))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEC: &str = r#"
+openapi: 3.0.0
+servers:
+  - url: https://api.test
+paths:
+  /items:
+    get:
+      operationId: list
+      parameters:
+        - { name: ids, in: query, required: true, schema: { type: array, items: { type: integer } }, example: [1, 2] }
+        - { name: filter, in: query, style: deepObject, example: { color: red } }
+        - { name: page, in: query, example: { number: 2 } }
+        - { name: point, in: query, explode: false, example: { x: 1 } }
+"#;
+
+    #[test]
+    fn array_and_object_query_params_become_strings() {
+        let profiles = parse_openapi(SPEC, None).unwrap();
+        let (_, profile) = &profiles[0];
+        assert_eq!(
+            profile.params,
+            json!({ "ids": "1,2", "filter[color]": "red", "number": "2", "point": "x,1" })
+        );
+    }
+
+    #[test]
+    fn dedup_suffixes_dont_collide_with_operation_names() {
+        let spec = r#"
+openapi: 3.0.0
+servers:
+  - url: https://api.test
+paths:
+  /a:
+    get: {}
+  /a/2:
+    get: {}
+  /b:
+    get: { operationId: get-a }
+"#;
+        let names: Vec<String> = parse_openapi(spec, None)
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["get-a", "get-a-2", "get-a-3"]);
+    }
+}
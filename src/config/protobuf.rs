@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use anyhow::{anyhow, Context, Result};
+use mime::Mime;
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor};
+use serde::{Deserialize, Serialize};
+
+/// content types of the bodies that are decoded, anything else is left as it is
+const CONTENT_TYPES: &[&str] = &["application/x-protobuf", "application/protobuf"];
+
+/// descriptor pools by path, read when the config is validated
+static POOLS: OnceLock<Mutex<HashMap<String, DescriptorPool>>> = OnceLock::new();
+
+/// a protobuf response body decoded into json before it's filtered, when its content type
+/// is `application/x-protobuf` or `application/protobuf`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ProtobufMessage {
+    /// path of a compiled `FileDescriptorSet`, e.g. from `protoc --include_imports -o`
+    pub descriptor_set: String,
+    /// full name of the message type, e.g. `package.Reply`
+    pub message: String,
+}
+
+impl ProtobufMessage {
+    pub(crate) fn validate(&self) -> Result<()> {
+        self.descriptor().map(|_| ())
+    }
+
+    fn descriptor(&self) -> Result<MessageDescriptor> {
+        load_descriptor_pool(&self.descriptor_set, "protobuf")?
+            .get_message_by_name(&self.message)
+            .ok_or_else(|| anyhow!("protobuf.message: 找不到 message {}", self.message))
+    }
+
+    pub(super) fn accepts(mime: Option<&Mime>) -> bool {
+        mime.is_some_and(|m| CONTENT_TYPES.contains(&m.essence_str()))
+    }
+
+    /// the body as canonical protobuf json
+    pub fn decode_json(&self, bytes: &[u8]) -> Result<String> {
+        let message = DynamicMessage::decode(self.descriptor()?, bytes)
+            .with_context(|| format!("body is not a valid {}", self.message))?;
+        Ok(serde_json::to_string(&message)?)
+    }
+}
+
+/// read from disk the first time only, `field` names the config key in error messages
+pub(super) fn load_descriptor_pool(path: &str, field: &str) -> Result<DescriptorPool> {
+    let pools = POOLS.get_or_init(Default::default);
+    if let Some(pool) = pools.lock().unwrap().get(path) {
+        return Ok(pool.clone());
+    }
+    let bytes = std::fs::read(path)
+        .with_context(|| format!("{}.descriptor_set: {} 文件不存在", field, path))?;
+    let pool = DescriptorPool::decode(bytes.as_slice()).with_context(|| {
+        format!(
+            "{}.descriptor_set: {} 不是有效的 FileDescriptorSet",
+            field, path
+        )
+    })?;
+    pools.lock().unwrap().insert(path.to_string(), pool.clone());
+    Ok(pool)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_protobuf_content_types_are_decoded() {
+        let accepts = |mime: &str| ProtobufMessage::accepts(mime.parse::<Mime>().ok().as_ref());
+        assert!(accepts("application/x-protobuf"));
+        assert!(accepts("application/protobuf; proto=pkg.Reply"));
+        assert!(!accepts("text/html; charset=utf-8"));
+        assert!(!accepts("application/json"));
+        assert!(!ProtobufMessage::accepts(None));
+    }
+}
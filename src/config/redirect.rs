@@ -0,0 +1,173 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::anyhow;
+use reqwest::{redirect::Policy, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::Url;
+
+/// same limit as the reqwest default policy
+pub(crate) const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// `none` or the max number of redirects to follow
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "Value", into = "Value")]
+pub enum FollowRedirects {
+    None,
+    Limit(usize),
+}
+
+impl TryFrom<Value> for FollowRedirects {
+    type Error = anyhow::Error;
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(s) if s == "none" => Ok(Self::None),
+            Value::Number(n) => n
+                .as_u64()
+                .map(|n| Self::Limit(n as usize))
+                .ok_or_else(|| anyhow!("follow_redirects: {} 不是有效的数字", n)),
+            v => Err(anyhow!("follow_redirects: {} 应为 none 或数字", v)),
+        }
+    }
+}
+
+impl From<FollowRedirects> for Value {
+    fn from(value: FollowRedirects) -> Self {
+        match value {
+            FollowRedirects::None => Value::String("none".to_string()),
+            FollowRedirects::Limit(n) => Value::from(n),
+        }
+    }
+}
+
+/// a redirect response that was followed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectHop {
+    pub status: StatusCode,
+    pub location: Url,
+}
+
+pub type RedirectChain = Arc<Mutex<Vec<RedirectHop>>>;
+
+/// build a policy that records every followed hop into `chain`
+pub fn redirect_policy(follow: Option<FollowRedirects>, chain: RedirectChain) -> Policy {
+    let (max, error_on_limit) = match follow {
+        Some(FollowRedirects::None) => return Policy::none(),
+        Some(FollowRedirects::Limit(max)) => (max, false),
+        None => (DEFAULT_MAX_REDIRECTS, true),
+    };
+
+    Policy::custom(move |attempt| {
+        // `previous` holds the original url and every hop followed so far
+        if attempt.previous().len() > max {
+            return if error_on_limit {
+                attempt.error("too many redirects")
+            } else {
+                attempt.stop()
+            };
+        }
+        chain.lock().unwrap().push(RedirectHop {
+            status: attempt.status(),
+            location: attempt.url().clone(),
+        });
+        attempt.follow()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::Infallible, net::SocketAddr};
+
+    use hyper::{
+        service::{make_service_fn, service_fn},
+        Body, Request, Response, Server,
+    };
+
+    use super::*;
+
+    /// `/n` redirects to `/n-1` until `/0`, which answers 200
+    fn countdown_server() -> SocketAddr {
+        let make = make_service_fn(|_| async {
+            Ok::<_, Infallible>(service_fn(|req: Request<Body>| async move {
+                let n: usize = req.uri().path()[1..].parse().unwrap_or(0);
+                let res = if n == 0 {
+                    Response::new(Body::from("done"))
+                } else {
+                    Response::builder()
+                        .status(302)
+                        .header("location", format!("/{}", n - 1))
+                        .body(Body::empty())
+                        .unwrap()
+                };
+                Ok::<_, Infallible>(res)
+            }))
+        });
+        let server = Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(make);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        addr
+    }
+
+    async fn get(
+        follow: Option<FollowRedirects>,
+        hops: usize,
+    ) -> (reqwest::Result<reqwest::Response>, Vec<RedirectHop>) {
+        let addr = countdown_server();
+        let chain = RedirectChain::default();
+        let client = reqwest::Client::builder()
+            .redirect(redirect_policy(follow, chain.clone()))
+            .build()
+            .unwrap();
+        let res = client.get(format!("http://{}/{}", addr, hops)).send().await;
+        let chain = chain.lock().unwrap().clone();
+        (res, chain)
+    }
+
+    #[test]
+    fn follow_redirects_is_none_or_a_number() {
+        let parse = |yaml: &str| serde_yaml::from_str::<FollowRedirects>(yaml);
+        assert_eq!(parse("none").unwrap(), FollowRedirects::None);
+        assert_eq!(parse("3").unwrap(), FollowRedirects::Limit(3));
+        assert!(parse("all").is_err());
+        assert!(parse("-1").is_err());
+        assert_eq!(
+            serde_yaml::to_string(&FollowRedirects::None).unwrap(),
+            "none\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn every_followed_hop_is_recorded() {
+        let (res, chain) = get(None, 2).await;
+        assert_eq!(res.unwrap().status(), StatusCode::OK);
+        let paths: Vec<&str> = chain.iter().map(|hop| hop.location.path()).collect();
+        assert_eq!(paths, ["/1", "/0"]);
+        assert!(chain.iter().all(|hop| hop.status == StatusCode::FOUND));
+    }
+
+    #[tokio::test]
+    async fn none_returns_the_first_redirect() {
+        let (res, chain) = get(Some(FollowRedirects::None), 2).await;
+        assert_eq!(res.unwrap().status(), StatusCode::FOUND);
+        assert!(chain.is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_limit_stops_at_the_redirect_past_it() {
+        let (res, chain) = get(Some(FollowRedirects::Limit(2)), 5).await;
+        let res = res.unwrap();
+        assert_eq!(res.status(), StatusCode::FOUND);
+        assert_eq!(res.url().path(), "/3");
+        assert_eq!(chain.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn the_default_limit_is_an_error() {
+        let (res, chain) = get(None, DEFAULT_MAX_REDIRECTS + 1).await;
+        assert!(res.unwrap_err().is_redirect());
+        assert_eq!(chain.len(), DEFAULT_MAX_REDIRECTS);
+
+        let (res, _) = get(None, DEFAULT_MAX_REDIRECTS).await;
+        assert_eq!(res.unwrap().status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,418 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+use super::{DiffProfile, RequestProfile, ResponseProfile};
+use crate::ExtraArgs;
+
+/// what differs between runs of the same request, each with its number of distinct values
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Variance {
+    pub runs: usize,
+    /// every status seen, empty when it never changed
+    pub statuses: Vec<u16>,
+    pub headers: Vec<(String, usize)>,
+    /// paths of the json values that vary, e.g. `items[0].id`
+    pub fields: Vec<(String, usize)>,
+    /// distinct bodies that are not json, 0 when they never changed
+    pub bodies: usize,
+}
+
+/// the variance of both sides and the `res` that skips what varies
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RepeatResult {
+    pub output: String,
+    /// `req1: 2 header(s), 1 field(s) vary over 5 runs`
+    pub failures: Vec<String>,
+    pub suggested: ResponseProfile,
+    /// keys `suggested` skips as a whole though only fields nested in them vary
+    pub warnings: Vec<String>,
+}
+
+impl RequestProfile {
+    /// send the request `runs` times and compare the responses with each other
+    pub async fn variance(&self, args: &ExtraArgs, runs: usize) -> Result<Variance> {
+        if self.websocket.is_some() || self.grpc.is_some() {
+            return Err(anyhow!("repeat mode only works for http profiles"));
+        }
+        let mut statuses = vec![];
+        let mut headers: Vec<BTreeMap<String, String>> = vec![];
+        let mut bodies = vec![];
+        for _ in 0..runs {
+            let res = self.send(args).await?;
+            statuses.push(res.response().status().as_u16());
+            let mut values = BTreeMap::<String, String>::new();
+            for (k, v) in res.response().headers() {
+                let v = String::from_utf8_lossy(v.as_bytes());
+                values
+                    .entry(k.to_string())
+                    .and_modify(|s| *s = format!("{}, {}", s, v))
+                    .or_insert_with(|| v.into_owned());
+            }
+            headers.push(values);
+            bodies.push(res.body_text().await?);
+        }
+
+        let mut variance = Variance {
+            runs,
+            ..Variance::default()
+        };
+        if distinct(statuses.iter().map(Some)) > 1 {
+            statuses.sort_unstable();
+            statuses.dedup();
+            variance.statuses = statuses;
+        }
+        variance.headers = varying(&headers);
+        let json = bodies
+            .iter()
+            .map(|body| serde_json::from_str::<Value>(body).ok())
+            .collect::<Option<Vec<_>>>();
+        match json {
+            Some(values) => {
+                let fields = values
+                    .iter()
+                    .map(|value| {
+                        let mut fields = BTreeMap::new();
+                        flatten(value, String::new(), &mut fields);
+                        fields
+                    })
+                    .collect::<Vec<_>>();
+                variance.fields = varying(&fields);
+            }
+            None => {
+                let count = distinct(bodies.iter().map(Some));
+                variance.bodies = if count > 1 { count } else { 0 };
+            }
+        }
+        Ok(variance)
+    }
+}
+
+impl Variance {
+    pub fn is_empty(&self) -> bool {
+        self.statuses.is_empty()
+            && self.headers.is_empty()
+            && self.fields.is_empty()
+            && self.bodies == 0
+    }
+
+    /// what varies, e.g. `status, 2 header(s), 3 field(s)`
+    fn parts(&self) -> Vec<String> {
+        let mut parts = vec![];
+        if !self.statuses.is_empty() {
+            parts.push("status".to_string());
+        }
+        if !self.headers.is_empty() {
+            parts.push(format!("{} header(s)", self.headers.len()));
+        }
+        if !self.fields.is_empty() {
+            parts.push(format!("{} field(s)", self.fields.len()));
+        }
+        if self.bodies > 0 {
+            parts.push("body".to_string());
+        }
+        parts
+    }
+
+    /// top level body keys with a varying value, each with the nested paths that vary in it,
+    /// `skip_body` can't skip less than the whole key
+    fn top_level_keys(&self) -> Vec<(String, Vec<String>)> {
+        let mut keys: Vec<(String, Vec<String>)> = vec![];
+        for (path, _) in &self.fields {
+            let key = path.split(['.', '[']).next().unwrap_or_default();
+            if key.is_empty() {
+                continue;
+            }
+            let index = match keys.iter().position(|(k, _)| k == key) {
+                Some(index) => index,
+                None => {
+                    keys.push((key.to_string(), vec![]));
+                    keys.len() - 1
+                }
+            };
+            if path != key {
+                keys[index].1.push(path.clone());
+            }
+        }
+        keys
+    }
+}
+
+impl DiffProfile {
+    /// send each side `runs` times and report what varies between runs of the same side
+    pub async fn repeat(&self, args: &ExtraArgs, runs: usize) -> Result<RepeatResult> {
+        if runs < 2 {
+            return Err(anyhow!("repeat 次数必须大于 1"));
+        }
+        let mut result = RepeatResult {
+            suggested: self.res.clone(),
+            ..RepeatResult::default()
+        };
+        for (name, req) in [("req1", &self.req1), ("req2", &self.req2)] {
+            let variance = req.variance(args, runs).await?;
+            writeln!(result.output, "======== {} ({} runs) ========", name, runs)?;
+            write!(result.output, "{}", variance_text(&variance)?)?;
+            if variance.is_empty() {
+                continue;
+            }
+            result.failures.push(format!(
+                "{}: {} vary over {} runs",
+                name,
+                variance.parts().join(", "),
+                runs
+            ));
+            let suggested = &mut result.suggested;
+            for (key, nested) in variance.top_level_keys() {
+                if suggested.skip_body.contains(&key) {
+                    continue;
+                }
+                if !nested.is_empty() {
+                    result.warnings.push(format!(
+                        "all of {} is skipped because of {}",
+                        key,
+                        nested.join(", ")
+                    ));
+                }
+                suggested.skip_body.push(key);
+            }
+            // skip_headers lists the headers that are diffed, so varying ones are taken out
+            suggested
+                .skip_headers
+                .retain(|h| !variance.headers.iter().any(|(name, _)| name == h));
+        }
+        Ok(result)
+    }
+}
+
+fn variance_text(variance: &Variance) -> Result<String> {
+    let mut output = String::new();
+    if variance.is_empty() {
+        writeln!(output, "no variance")?;
+        return Ok(output);
+    }
+    if !variance.statuses.is_empty() {
+        let statuses: Vec<String> = variance.statuses.iter().map(|s| s.to_string()).collect();
+        writeln!(output, "status varies: {}", statuses.join(", "))?;
+    }
+    for (name, count) in &variance.headers {
+        writeln!(output, "header {} has {} distinct values", name, count)?;
+    }
+    for (path, count) in &variance.fields {
+        writeln!(output, "field {} has {} distinct values", path, count)?;
+    }
+    if variance.bodies > 0 {
+        writeln!(output, "body has {} distinct values", variance.bodies)?;
+    }
+    Ok(output)
+}
+
+/// the keys whose value isn't the same in every run, a key missing from a run counts as a value
+fn varying(runs: &[BTreeMap<String, String>]) -> Vec<(String, usize)> {
+    let mut keys: Vec<&String> = runs.iter().flat_map(|run| run.keys()).collect();
+    keys.sort_unstable();
+    keys.dedup();
+    keys.into_iter()
+        .filter_map(|key| {
+            let count = distinct(runs.iter().map(|run| run.get(key)));
+            (count > 1).then(|| (key.clone(), count))
+        })
+        .collect()
+}
+
+fn distinct<T: Eq + std::hash::Hash>(values: impl Iterator<Item = Option<T>>) -> usize {
+    values.collect::<HashSet<_>>().len()
+}
+
+/// every leaf of `value` by its path, `a.b[0]`
+fn flatten(value: &Value, path: String, fields: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Object(obj) if !obj.is_empty() => {
+            for (k, v) in obj {
+                let path = if path.is_empty() {
+                    k.clone()
+                } else {
+                    format!("{}.{}", path, k)
+                };
+                flatten(v, path, fields);
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for (i, v) in items.iter().enumerate() {
+                flatten(v, format!("{}[{}]", path, i), fields);
+            }
+        }
+        v => {
+            fields.insert(path, v.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use hyper::{
+        service::{make_service_fn, service_fn},
+        Body, Response, Server,
+    };
+    use serde_json::json;
+
+    use super::*;
+    use crate::{DiffConfig, LoadConfig};
+
+    fn run(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    /// a new `x-request-id` and nested item id on every request, the rest stays the same
+    fn counter_server() -> SocketAddr {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let make = make_service_fn(move |_| {
+            let hits = hits.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |_req| {
+                    let n = hits.fetch_add(1, Ordering::SeqCst);
+                    let body = json!({"name": "a", "items": [{"id": n, "name": "b"}]});
+                    async move {
+                        Ok::<_, Infallible>(
+                            Response::builder()
+                                .header("x-request-id", n.to_string())
+                                .header("x-env", "test")
+                                .body(Body::from(body.to_string()))
+                                .unwrap(),
+                        )
+                    }
+                }))
+            }
+        });
+        let server = Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(make);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        addr
+    }
+
+    #[test]
+    fn leaves_are_flattened_by_path() {
+        let mut fields = BTreeMap::new();
+        let value = json!({"a": {"b": [1, {"c": null}], "d": {}}, "e": [], "f": "x"});
+        flatten(&value, String::new(), &mut fields);
+        assert_eq!(
+            fields,
+            run(&[
+                ("a.b[0]", "1"),
+                ("a.b[1].c", "null"),
+                ("a.d", "{}"),
+                ("e", "[]"),
+                ("f", "\"x\""),
+            ])
+        );
+
+        let mut fields = BTreeMap::new();
+        flatten(&json!([{"id": 1}]), String::new(), &mut fields);
+        assert_eq!(fields, run(&[("[0].id", "1")]));
+    }
+
+    #[test]
+    fn varying_counts_the_distinct_values_of_each_key() {
+        let runs = [
+            run(&[("a", "1"), ("b", "x"), ("c", "1")]),
+            run(&[("a", "2"), ("b", "x")]),
+            run(&[("a", "2"), ("b", "x"), ("c", "1")]),
+        ];
+        // a key missing from a run is a value of its own
+        assert_eq!(varying(&runs), [("a".to_string(), 2), ("c".to_string(), 2)]);
+        assert!(varying(&runs[1..2]).is_empty());
+        assert!(varying(&[]).is_empty());
+    }
+
+    #[test]
+    fn nested_fields_are_reported_with_their_top_level_key() {
+        let variance = Variance {
+            runs: 3,
+            fields: vec![
+                ("at".to_string(), 3),
+                ("items[0].id".to_string(), 3),
+                ("items[1].id".to_string(), 2),
+                ("[0]".to_string(), 2),
+            ],
+            ..Variance::default()
+        };
+        assert_eq!(
+            variance.top_level_keys(),
+            [
+                ("at".to_string(), vec![]),
+                (
+                    "items".to_string(),
+                    vec!["items[0].id".to_string(), "items[1].id".to_string()]
+                ),
+            ]
+        );
+        assert_eq!(variance.parts(), ["4 field(s)"]);
+    }
+
+    #[test]
+    fn variance_is_described_line_by_line() {
+        let variance = Variance {
+            runs: 3,
+            statuses: vec![200, 503],
+            headers: vec![("date".to_string(), 3)],
+            fields: vec![],
+            bodies: 2,
+        };
+        assert_eq!(
+            variance_text(&variance).unwrap(),
+            "status varies: 200, 503\nheader date has 3 distinct values\nbody has 2 distinct values\n"
+        );
+        assert_eq!(variance.parts(), ["status", "1 header(s)", "body"]);
+        assert_eq!(
+            variance_text(&Variance::default()).unwrap(),
+            "no variance\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn repeat_suggests_skipping_what_varies() -> Result<()> {
+        let addr = counter_server();
+        let config = DiffConfig::from_yaml(&format!(
+            "a:\n  req1:\n    url: http://{addr}/\n  req2:\n    url: http://{addr}/\n  res:\n    skip_headers: [x-request-id, x-env]\n",
+            addr = addr
+        ))?;
+        let profile = config.get_profile("a").unwrap();
+        let result = profile.repeat(&ExtraArgs::default(), 3).await?;
+        assert!(
+            result
+                .output
+                .contains("header x-request-id has 3 distinct values"),
+            "{}",
+            result.output
+        );
+        assert!(result
+            .output
+            .contains("field items[0].id has 3 distinct values"));
+        assert_eq!(
+            result.failures,
+            [
+                "req1: 1 header(s), 1 field(s) vary over 3 runs",
+                "req2: 1 header(s), 1 field(s) vary over 3 runs",
+            ]
+        );
+        assert_eq!(result.suggested.skip_body, ["items"]);
+        assert_eq!(result.suggested.skip_headers, ["x-env"]);
+        // req2 finds items already skipped
+        assert_eq!(
+            result.warnings,
+            ["all of items is skipped because of items[0].id"]
+        );
+
+        assert!(profile.repeat(&ExtraArgs::default(), 1).await.is_err());
+        Ok(())
+    }
+}
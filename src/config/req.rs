@@ -5,15 +5,23 @@ use serde::{Deserialize, Serialize};
 use crate::RequestProfile;
 use anyhow::{Context, Result};
 
-use super::{LoadConfig, ValidateConfig};
+use super::{is_default, LoadConfig, RequestDefaults, ValidateConfig};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct RequestConfig {
     #[serde(flatten)]
     pub profiles: HashMap<String, RequestProfile>,
+    #[serde(skip_serializing_if = "is_default", default)]
+    pub defaults: RequestDefaults,
 }
 
-impl LoadConfig for RequestConfig {}
+impl LoadConfig for RequestConfig {
+    fn apply_defaults(&mut self) {
+        for profile in self.profiles.values_mut() {
+            profile.apply_defaults(&self.defaults);
+        }
+    }
+}
 impl ValidateConfig for RequestConfig {
     fn validate(&self) -> Result<()> {
         for (name, propfile) in &self.profiles {
@@ -25,7 +33,10 @@ impl ValidateConfig for RequestConfig {
 
 impl RequestConfig {
     pub fn new(profiles: HashMap<String, RequestProfile>) -> Self {
-        Self { profiles }
+        Self {
+            profiles,
+            defaults: RequestDefaults::default(),
+        }
     }
     pub fn get_profile(&self, name: &str) -> Option<&RequestProfile> {
         self.profiles.get(name)
@@ -0,0 +1,200 @@
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+/// timeout and retry settings of a request
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetryProfile {
+    /// request timeout in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub timeout: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub retries: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub retry_on: Option<Vec<RetryOn>>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub backoff: Option<Backoff>,
+}
+
+/// a status code or an error kind that triggers a retry
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum RetryOn {
+    Status(u16),
+    Error(RetryError),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryError {
+    Connect,
+    Timeout,
+}
+
+/// exponential backoff between attempts, all durations in milliseconds
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Backoff {
+    #[serde(default = "default_initial")]
+    pub initial: u64,
+    #[serde(default = "default_multiplier")]
+    pub multiplier: u32,
+    #[serde(default = "default_max")]
+    pub max: u64,
+}
+
+fn default_initial() -> u64 {
+    200
+}
+
+fn default_multiplier() -> u32 {
+    2
+}
+
+fn default_max() -> u64 {
+    5000
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: default_initial(),
+            multiplier: default_multiplier(),
+            max: default_max(),
+        }
+    }
+}
+
+fn default_retry_on() -> Vec<RetryOn> {
+    vec![
+        RetryOn::Error(RetryError::Connect),
+        RetryOn::Error(RetryError::Timeout),
+        RetryOn::Status(502),
+        RetryOn::Status(503),
+        RetryOn::Status(504),
+    ]
+}
+
+impl RetryProfile {
+    /// fill the unset fields from the config-wide defaults
+    pub fn merge(&mut self, defaults: &RetryProfile) {
+        if self.timeout.is_none() {
+            self.timeout = defaults.timeout;
+        }
+        if self.retries.is_none() {
+            self.retries = defaults.retries;
+        }
+        if self.retry_on.is_none() {
+            self.retry_on = defaults.retry_on.clone();
+        }
+        if self.backoff.is_none() {
+            self.backoff = defaults.backoff.clone();
+        }
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout.map(Duration::from_millis)
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.retries.unwrap_or(0) + 1
+    }
+
+    pub fn should_retry_status(&self, status: StatusCode) -> bool {
+        self.retry_on()
+            .iter()
+            .any(|r| matches!(r, RetryOn::Status(code) if *code == status.as_u16()))
+    }
+
+    pub fn should_retry_error(&self, err: &reqwest::Error) -> bool {
+        self.retry_on().iter().any(|r| match r {
+            RetryOn::Error(RetryError::Connect) => err.is_connect(),
+            RetryOn::Error(RetryError::Timeout) => err.is_timeout(),
+            RetryOn::Status(_) => false,
+        })
+    }
+
+    /// delay before the next attempt, `attempt` starts from 1
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let backoff = self.backoff.clone().unwrap_or_default();
+        let factor = (backoff.multiplier as u64).saturating_pow(attempt.saturating_sub(1));
+        Duration::from_millis(backoff.initial.saturating_mul(factor).min(backoff.max))
+    }
+
+    fn retry_on(&self) -> Vec<RetryOn> {
+        self.retry_on.clone().unwrap_or_else(default_retry_on)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_grows_by_the_multiplier_up_to_max() {
+        let retry = RetryProfile {
+            backoff: Some(Backoff {
+                initial: 100,
+                multiplier: 3,
+                max: 1000,
+            }),
+            ..RetryProfile::default()
+        };
+        let delays: Vec<u64> = (1..=4).map(|a| retry.delay(a).as_millis() as u64).collect();
+        assert_eq!(delays, [100, 300, 900, 1000]);
+        // a huge attempt count saturates instead of overflowing
+        assert_eq!(retry.delay(u32::MAX), Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn default_backoff_starts_at_200ms() {
+        let retry = RetryProfile::default();
+        assert_eq!(retry.delay(1), Duration::from_millis(200));
+        assert_eq!(retry.delay(2), Duration::from_millis(400));
+        assert_eq!(retry.delay(10), Duration::from_millis(5000));
+    }
+
+    #[test]
+    fn retry_on_status_uses_the_list_or_the_gateway_errors() {
+        let retry = RetryProfile::default();
+        assert!(retry.should_retry_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!retry.should_retry_status(StatusCode::INTERNAL_SERVER_ERROR));
+
+        let retry: RetryProfile = serde_yaml::from_str("retry_on: [500, connect]").unwrap();
+        assert_eq!(
+            retry.retry_on,
+            Some(vec![
+                RetryOn::Status(500),
+                RetryOn::Error(RetryError::Connect)
+            ])
+        );
+        assert!(retry.should_retry_status(StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(!retry.should_retry_status(StatusCode::SERVICE_UNAVAILABLE));
+    }
+
+    #[test]
+    fn attempts_count_the_first_request() {
+        assert_eq!(RetryProfile::default().max_attempts(), 1);
+        let retry = RetryProfile {
+            retries: Some(2),
+            ..RetryProfile::default()
+        };
+        assert_eq!(retry.max_attempts(), 3);
+    }
+
+    #[test]
+    fn merge_keeps_the_profile_values() {
+        let mut retry = RetryProfile {
+            timeout: Some(100),
+            ..RetryProfile::default()
+        };
+        retry.merge(&RetryProfile {
+            timeout: Some(5000),
+            retries: Some(3),
+            ..RetryProfile::default()
+        });
+        assert_eq!(retry.timeout, Some(100));
+        assert_eq!(retry.retries, Some(3));
+        assert_eq!(retry.timeout(), Some(Duration::from_millis(100)));
+    }
+}
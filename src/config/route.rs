@@ -0,0 +1,163 @@
+use std::net::{IpAddr, SocketAddr};
+
+use anyhow::{anyhow, Context, Result};
+use reqwest::{ClientBuilder, NoProxy, Proxy};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// where a request is sent to: proxy and curl-style `--resolve` overrides
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RouteProfile {
+    /// `host:port:addr[,addr]` entries, same as curl `--resolve`
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub resolve: Vec<String>,
+    /// http, https, socks5 or socks5h proxy url
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub proxy: Option<String>,
+    /// comma separated hosts that bypass the proxy
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub no_proxy: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolveEntry {
+    pub host: String,
+    pub port: u16,
+    pub addrs: Vec<IpAddr>,
+}
+
+impl RouteProfile {
+    /// fill the unset fields from the config-wide defaults
+    pub fn merge(&mut self, defaults: &RouteProfile) {
+        if self.resolve.is_empty() {
+            self.resolve = defaults.resolve.clone();
+        }
+        if self.proxy.is_none() {
+            self.proxy = defaults.proxy.clone();
+        }
+        if self.no_proxy.is_none() {
+            self.no_proxy = defaults.no_proxy.clone();
+        }
+    }
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        for entry in &self.resolve {
+            parse_resolve(entry)?;
+        }
+        if let Some(proxy) = &self.proxy {
+            Proxy::all(proxy.as_str())
+                .with_context(|| format!("proxy: {} 不是有效的代理地址", proxy))?;
+        }
+        Ok(())
+    }
+
+    /// apply the proxy and the resolve entries matching `url` to the client
+    pub fn configure(&self, mut builder: ClientBuilder, url: &Url) -> Result<ClientBuilder> {
+        if let Some(proxy) = &self.proxy {
+            let proxy = Proxy::all(proxy.as_str())?
+                .no_proxy(self.no_proxy.as_deref().and_then(NoProxy::from_string));
+            builder = builder.proxy(proxy);
+        }
+
+        let port = url.port_or_known_default();
+        for entry in &self.resolve {
+            let entry = parse_resolve(entry)?;
+            // reqwest always connects to the url port, entries for other ports are never used
+            if Some(entry.port) != port {
+                continue;
+            }
+            let addrs = entry
+                .addrs
+                .iter()
+                .map(|ip| SocketAddr::new(*ip, entry.port))
+                .collect::<Vec<_>>();
+            builder = builder.resolve_to_addrs(&entry.host, &addrs);
+        }
+
+        Ok(builder)
+    }
+}
+
+pub fn parse_resolve(s: &str) -> Result<ResolveEntry> {
+    let err = || anyhow!("resolve: {} 不是有效的 host:port:addr", s);
+    let mut parts = s.splitn(3, ':');
+    let host = parts.next().filter(|h| !h.is_empty()).ok_or_else(err)?;
+    let port = parts
+        .next()
+        .and_then(|p| p.parse::<u16>().ok())
+        .ok_or_else(err)?;
+    let addrs = parts
+        .next()
+        .ok_or_else(err)?
+        .split(',')
+        .map(|addr| {
+            addr.trim()
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse::<IpAddr>()
+                .map_err(|_| err())
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(ResolveEntry {
+        host: host.to_string(),
+        port,
+        addrs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_entries_parse_like_curl() {
+        let entry = parse_resolve("api.test:8443:127.0.0.1,[::1]").unwrap();
+        assert_eq!(entry.host, "api.test");
+        assert_eq!(entry.port, 8443);
+        assert_eq!(
+            entry.addrs,
+            [
+                "127.0.0.1".parse::<IpAddr>().unwrap(),
+                "::1".parse().unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_resolve_entries_are_rejected() {
+        for entry in [
+            "api.test",
+            ":443:127.0.0.1",
+            "api.test:port:127.0.0.1",
+            "api.test:443",
+            "api.test:443:not-an-ip",
+        ] {
+            assert!(parse_resolve(entry).is_err(), "{}", entry);
+        }
+    }
+
+    #[test]
+    fn validate_checks_the_proxy_url() {
+        let route = |yaml: &str| serde_yaml::from_str::<RouteProfile>(yaml).unwrap();
+        assert!(route("proxy: socks5h://127.0.0.1:1080").validate().is_ok());
+        assert!(route("proxy: 'http://[::1'").validate().is_err());
+        assert!(route("resolve: [api.test:443]").validate().is_err());
+    }
+
+    #[test]
+    fn merge_keeps_the_profile_values() {
+        let mut route = RouteProfile {
+            proxy: Some("http://own:8080".to_string()),
+            ..RouteProfile::default()
+        };
+        route.merge(&RouteProfile {
+            resolve: vec!["a.test:443:127.0.0.1".to_string()],
+            proxy: Some("http://default:8080".to_string()),
+            no_proxy: Some("localhost".to_string()),
+        });
+        assert_eq!(route.proxy.as_deref(), Some("http://own:8080"));
+        assert_eq!(route.resolve, ["a.test:443:127.0.0.1"]);
+        assert_eq!(route.no_proxy.as_deref(), Some("localhost"));
+    }
+}
@@ -0,0 +1,979 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use regex::Regex;
+use serde_json::Value;
+use url::Url;
+
+/// how many `$ref`s may be followed within each other
+const MAX_REF_DEPTH: usize = 256;
+
+/// keywords that would need more than this validator does, a schema using them is rejected
+/// instead of passing everything they should catch
+const UNSUPPORTED_KEYWORDS: &[&str] = &[
+    "unevaluatedProperties",
+    "unevaluatedItems",
+    "$dynamicRef",
+    "$recursiveRef",
+];
+
+/// the formats `matches_format` checks, any other is rejected
+const FORMATS: &[&str] = &[
+    "date",
+    "date-time",
+    "time",
+    "email",
+    "hostname",
+    "uuid",
+    "ipv4",
+    "ipv6",
+    "uri",
+    "url",
+    "regex",
+    "byte",
+    "binary",
+    "password",
+    "int32",
+    "int64",
+    "float",
+    "double",
+];
+
+/// keywords whose value is a schema, a list of schemas or, for `items`, either
+const SUBSCHEMA_KEYWORDS: &[&str] = &[
+    "not",
+    "if",
+    "then",
+    "else",
+    "contains",
+    "items",
+    "prefixItems",
+    "additionalItems",
+    "additionalProperties",
+    "propertyNames",
+    "allOf",
+    "anyOf",
+    "oneOf",
+];
+
+/// keywords whose value maps names to schemas
+const SUBSCHEMA_MAP_KEYWORDS: &[&str] = &[
+    "properties",
+    "patternProperties",
+    "dependentSchemas",
+    "dependencies",
+    "$defs",
+    "definitions",
+];
+
+/// the `$ref` targets being checked and the value each is checked against
+type Refs = Vec<(*const Value, *const Value)>;
+
+/// a value that doesn't match its schema, `pointer` is the JSON pointer of the value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    pub pointer: String,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.pointer.as_str() {
+            "" => write!(f, "/: {}", self.message),
+            pointer => write!(f, "{}: {}", pointer, self.message),
+        }
+    }
+}
+
+/// a json schema read from a json or yaml file
+#[derive(Debug, Clone)]
+pub struct JsonSchema {
+    root: Value,
+    /// files referenced by `$ref: other.json#/pointer`, relative to the root schema
+    documents: HashMap<String, Value>,
+    /// the `pattern`s and `patternProperties` names of every document, compiled
+    patterns: HashMap<String, Regex>,
+}
+
+/// schemas by path, loaded when the config is validated and shared by every response
+static LOADED: OnceLock<Mutex<HashMap<String, Arc<JsonSchema>>>> = OnceLock::new();
+
+impl JsonSchema {
+    /// the schema at `path`, read from disk the first time only
+    pub fn cached(path: &str) -> Result<Arc<Self>> {
+        let loaded = LOADED.get_or_init(Default::default);
+        if let Some(schema) = loaded.lock().unwrap().get(path) {
+            return Ok(schema.clone());
+        }
+        let schema = Arc::new(Self::load(path)?);
+        loaded
+            .lock()
+            .unwrap()
+            .insert(path.to_string(), schema.clone());
+        Ok(schema)
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let root = load_document(Path::new(path))?;
+        let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+        let mut documents = HashMap::new();
+        let mut pending = vec![];
+        collect_file_refs(&root, &mut pending);
+        while let Some(file) = pending.pop() {
+            if documents.contains_key(&file) {
+                continue;
+            }
+            let document = load_document(&dir.join(&file))
+                .with_context(|| format!("schema: {} 中的 $ref {} 无法读取", path, file))?;
+            collect_file_refs(&document, &mut pending);
+            documents.insert(file, document);
+        }
+        Self::new(root, documents).with_context(|| format!("schema: {}", path))
+    }
+
+    fn new(root: Value, documents: HashMap<String, Value>) -> Result<Self> {
+        let mut patterns = HashMap::new();
+        for document in std::iter::once(&root).chain(documents.values()) {
+            check_keywords(document, &mut patterns)?;
+        }
+        Ok(Self {
+            root,
+            documents,
+            patterns,
+        })
+    }
+
+    /// every violation of the schema, empty when `value` is valid
+    pub fn validate(&self, value: &Value) -> Vec<SchemaError> {
+        let mut errors = vec![];
+        self.check(&self.root, &self.root, value, "", &mut vec![], &mut errors);
+        errors
+    }
+
+    /// violations of a json body, a body that is not json is one violation
+    pub fn validate_text(&self, text: &str) -> Vec<SchemaError> {
+        match serde_json::from_str::<Value>(text) {
+            Ok(value) => self.validate(&value),
+            Err(e) => vec![error("", format!("body is not json: {}", e))],
+        }
+    }
+
+    fn is_valid(&self, doc: &Value, schema: &Value, value: &Value, refs: &mut Refs) -> bool {
+        let mut errors = vec![];
+        self.check(doc, schema, value, "", refs, &mut errors);
+        errors.is_empty()
+    }
+
+    /// the document holding the target, and the target itself
+    fn resolve<'a>(&'a self, doc: &'a Value, reference: &str) -> Option<(&'a Value, &'a Value)> {
+        let (file, pointer) = reference.split_once('#').unwrap_or((reference, ""));
+        let doc = match file {
+            "" => doc,
+            file => self.documents.get(file)?,
+        };
+        Some((doc, doc.pointer(pointer)?))
+    }
+
+    fn check(
+        &self,
+        doc: &Value,
+        schema: &Value,
+        value: &Value,
+        pointer: &str,
+        refs: &mut Refs,
+        errors: &mut Vec<SchemaError>,
+    ) {
+        let schema = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => {
+                return errors.push(error(pointer, "no value is allowed here".to_string()))
+            }
+            Value::Object(schema) => schema,
+            _ => return,
+        };
+
+        if let Some(reference) = schema.get("$ref").and_then(|r| r.as_str()) {
+            let Some((doc, target)) = self.resolve(doc, reference) else {
+                return errors.push(error(pointer, format!("$ref {} not found", reference)));
+            };
+            // the same target on the same value again would never end, `{"$ref": "#"}`
+            let key = (target as *const Value, value as *const Value);
+            if refs.contains(&key) {
+                return errors.push(error(pointer, format!("$ref {} is a cycle", reference)));
+            }
+            if refs.len() >= MAX_REF_DEPTH {
+                return errors.push(error(
+                    pointer,
+                    format!("$ref {} is nested too deep", reference),
+                ));
+            }
+            refs.push(key);
+            self.check(doc, target, value, pointer, refs, errors);
+            refs.pop();
+            // siblings of $ref are ignored before draft 2019-09, keep it simple and stop here
+            return;
+        }
+
+        if let Some(ty) = schema.get("type") {
+            let types: Vec<&str> = match ty {
+                Value::String(t) => vec![t.as_str()],
+                Value::Array(ts) => ts.iter().filter_map(|t| t.as_str()).collect(),
+                _ => vec![],
+            };
+            if !types.is_empty() && !types.iter().any(|t| is_type(value, t)) {
+                errors.push(error(
+                    pointer,
+                    format!("expected {}, got {}", types.join(" or "), type_name(value)),
+                ));
+            }
+        }
+        if let Some(Value::Array(values)) = schema.get("enum") {
+            if !values.contains(value) {
+                errors.push(error(
+                    pointer,
+                    format!("{} is not one of {}", value, Value::Array(values.clone())),
+                ));
+            }
+        }
+        if let Some(expected) = schema.get("const") {
+            if expected != value {
+                errors.push(error(
+                    pointer,
+                    format!("expected {}, got {}", expected, value),
+                ));
+            }
+        }
+        if let Some(format) = schema.get("format").and_then(|f| f.as_str()) {
+            if !matches_format(value, format) {
+                errors.push(error(
+                    pointer,
+                    format!("{} is not a valid {}", value, format),
+                ));
+            }
+        }
+
+        match value {
+            Value::Number(n) => {
+                let n = n.as_f64().unwrap_or_default();
+                let num = |key: &str| schema.get(key).and_then(|v| v.as_f64());
+                let flag = |key: &str| schema.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+                if let Some(min) = num("minimum") {
+                    // draft 4 used a boolean exclusiveMinimum next to minimum
+                    if n < min || (flag("exclusiveMinimum") && n == min) {
+                        errors.push(error(
+                            pointer,
+                            format!("{} is less than the minimum {}", n, min),
+                        ));
+                    }
+                }
+                if let Some(max) = num("maximum") {
+                    if n > max || (flag("exclusiveMaximum") && n == max) {
+                        errors.push(error(
+                            pointer,
+                            format!("{} is greater than the maximum {}", n, max),
+                        ));
+                    }
+                }
+                if let Some(min) = num("exclusiveMinimum") {
+                    if n <= min {
+                        errors.push(error(pointer, format!("{} is not greater than {}", n, min)));
+                    }
+                }
+                if let Some(max) = num("exclusiveMaximum") {
+                    if n >= max {
+                        errors.push(error(pointer, format!("{} is not less than {}", n, max)));
+                    }
+                }
+                if let Some(step) = num("multipleOf").filter(|s| *s > 0.0) {
+                    let ratio = n / step;
+                    if (ratio - ratio.round()).abs() > 1e-9 {
+                        errors.push(error(
+                            pointer,
+                            format!("{} is not a multiple of {}", n, step),
+                        ));
+                    }
+                }
+            }
+            Value::String(s) => {
+                if let Some(pattern) = schema.get("pattern").and_then(|p| p.as_str()) {
+                    if !self.patterns.get(pattern).is_some_and(|r| r.is_match(s)) {
+                        errors.push(error(
+                            pointer,
+                            format!("{:?} doesn't match the pattern {:?}", s, pattern),
+                        ));
+                    }
+                }
+                let len = s.chars().count() as u64;
+                if let Some(min) = schema.get("minLength").and_then(|v| v.as_u64()) {
+                    if len < min {
+                        errors.push(error(
+                            pointer,
+                            format!("length {} is shorter than {}", len, min),
+                        ));
+                    }
+                }
+                if let Some(max) = schema.get("maxLength").and_then(|v| v.as_u64()) {
+                    if len > max {
+                        errors.push(error(
+                            pointer,
+                            format!("length {} is longer than {}", len, max),
+                        ));
+                    }
+                }
+            }
+            Value::Array(items) => {
+                let len = items.len() as u64;
+                if let Some(min) = schema.get("minItems").and_then(|v| v.as_u64()) {
+                    if len < min {
+                        errors.push(error(
+                            pointer,
+                            format!("{} items, expected at least {}", len, min),
+                        ));
+                    }
+                }
+                if let Some(max) = schema.get("maxItems").and_then(|v| v.as_u64()) {
+                    if len > max {
+                        errors.push(error(
+                            pointer,
+                            format!("{} items, expected at most {}", len, max),
+                        ));
+                    }
+                }
+                if schema.get("uniqueItems").and_then(|v| v.as_bool()) == Some(true) {
+                    for (i, item) in items.iter().enumerate() {
+                        if items[..i].contains(item) {
+                            errors.push(error(pointer, format!("item {} is a duplicate", i)));
+                        }
+                    }
+                }
+                if let Some(contains) = schema.get("contains") {
+                    let matches = items
+                        .iter()
+                        .filter(|item| self.is_valid(doc, contains, item, refs))
+                        .count() as u64;
+                    let min = schema.get("minContains").and_then(|v| v.as_u64());
+                    let max = schema.get("maxContains").and_then(|v| v.as_u64());
+                    if matches < min.unwrap_or(1) {
+                        errors.push(error(
+                            pointer,
+                            format!(
+                                "{} items match `contains`, expected at least {}",
+                                matches,
+                                min.unwrap_or(1)
+                            ),
+                        ));
+                    }
+                    if let Some(max) = max.filter(|max| matches > *max) {
+                        errors.push(error(
+                            pointer,
+                            format!(
+                                "{} items match `contains`, expected at most {}",
+                                matches, max
+                            ),
+                        ));
+                    }
+                }
+            }
+            Value::Object(obj) => {
+                let len = obj.len() as u64;
+                if let Some(min) = schema.get("minProperties").and_then(|v| v.as_u64()) {
+                    if len < min {
+                        errors.push(error(
+                            pointer,
+                            format!("{} properties, expected at least {}", len, min),
+                        ));
+                    }
+                }
+                if let Some(max) = schema.get("maxProperties").and_then(|v| v.as_u64()) {
+                    if len > max {
+                        errors.push(error(
+                            pointer,
+                            format!("{} properties, expected at most {}", len, max),
+                        ));
+                    }
+                }
+                if let Some(Value::Array(required)) = schema.get("required") {
+                    for key in required.iter().filter_map(|k| k.as_str()) {
+                        if !obj.contains_key(key) {
+                            errors.push(error(
+                                pointer,
+                                format!("missing required property `{}`", key),
+                            ));
+                        }
+                    }
+                }
+                if let Some(Value::Object(dependent)) = schema.get("dependentRequired") {
+                    for (key, required) in dependent.iter().filter(|(k, _)| obj.contains_key(*k)) {
+                        for other in required.as_array().into_iter().flatten() {
+                            let other = other.as_str().unwrap_or_default();
+                            if !obj.contains_key(other) {
+                                errors.push(error(
+                                    pointer,
+                                    format!("`{}` requires property `{}`", key, other),
+                                ));
+                            }
+                        }
+                    }
+                }
+                if let Some(names) = schema.get("propertyNames") {
+                    for key in obj.keys() {
+                        if !self.is_valid(doc, names, &Value::String(key.clone()), refs) {
+                            errors.push(error(
+                                pointer,
+                                format!("property name `{}` doesn't match `propertyNames`", key),
+                            ));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        // keywords that look into the children
+        match value {
+            Value::Array(items) => {
+                let prefix = match (schema.get("prefixItems"), schema.get("items")) {
+                    (Some(Value::Array(prefix)), _) => prefix.as_slice(),
+                    // draft 7 tuple form of items
+                    (None, Some(Value::Array(prefix))) => prefix.as_slice(),
+                    _ => &[],
+                };
+                let rest = match (schema.get("prefixItems"), schema.get("items")) {
+                    (Some(_), items) => items,
+                    (None, Some(Value::Array(_))) => schema.get("additionalItems"),
+                    (None, items) => items,
+                };
+                for (i, item) in items.iter().enumerate() {
+                    let child = format!("{}/{}", pointer, i);
+                    if let Some(item_schema) = prefix.get(i).or(rest) {
+                        self.check(doc, item_schema, item, &child, refs, errors);
+                    }
+                }
+            }
+            Value::Object(obj) => {
+                let properties = schema.get("properties").and_then(|p| p.as_object());
+                let pattern_properties: Vec<(&Regex, &Value)> = schema
+                    .get("patternProperties")
+                    .and_then(|p| p.as_object())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|(pattern, s)| Some((self.patterns.get(pattern)?, s)))
+                    .collect();
+                for (key, child_value) in obj {
+                    let child = format!("{}/{}", pointer, escape_pointer(key));
+                    let mut matched = false;
+                    if let Some(prop_schema) = properties.and_then(|p| p.get(key)) {
+                        matched = true;
+                        self.check(doc, prop_schema, child_value, &child, refs, errors);
+                    }
+                    for (regex, prop_schema) in &pattern_properties {
+                        if regex.is_match(key) {
+                            matched = true;
+                            self.check(doc, prop_schema, child_value, &child, refs, errors);
+                        }
+                    }
+                    if matched {
+                        continue;
+                    }
+                    match schema.get("additionalProperties") {
+                        Some(Value::Bool(false)) => errors.push(SchemaError {
+                            pointer: child,
+                            message: "additional property is not allowed".to_string(),
+                        }),
+                        Some(extra) => self.check(doc, extra, child_value, &child, refs, errors),
+                        None => {}
+                    }
+                }
+                // draft 7 `dependencies` holds both forms of the later `dependent*` keywords
+                let dependent = ["dependentSchemas", "dependencies"]
+                    .iter()
+                    .filter_map(|k| schema.get(*k).and_then(|d| d.as_object()))
+                    .flatten()
+                    .filter(|(key, _)| obj.contains_key(*key));
+                for (key, dependency) in dependent {
+                    match dependency {
+                        Value::Array(required) => {
+                            for other in required.iter().filter_map(|o| o.as_str()) {
+                                if !obj.contains_key(other) {
+                                    errors.push(error(
+                                        pointer,
+                                        format!("`{}` requires property `{}`", key, other),
+                                    ));
+                                }
+                            }
+                        }
+                        dependency => self.check(doc, dependency, value, pointer, refs, errors),
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(Value::Array(all)) = schema.get("allOf") {
+            for s in all {
+                self.check(doc, s, value, pointer, refs, errors);
+            }
+        }
+        if let Some(Value::Array(any)) = schema.get("anyOf") {
+            if !any.iter().any(|s| self.is_valid(doc, s, value, refs)) {
+                errors.push(error(
+                    pointer,
+                    "doesn't match any schema of `anyOf`".to_string(),
+                ));
+            }
+        }
+        if let Some(Value::Array(one)) = schema.get("oneOf") {
+            let matches = one
+                .iter()
+                .filter(|s| self.is_valid(doc, s, value, refs))
+                .count();
+            if matches != 1 {
+                errors.push(error(
+                    pointer,
+                    format!("matches {} schemas of `oneOf`, expected exactly 1", matches),
+                ));
+            }
+        }
+        if let Some(not) = schema.get("not") {
+            if self.is_valid(doc, not, value, refs) {
+                errors.push(error(pointer, "matches the schema of `not`".to_string()));
+            }
+        }
+        if let Some(condition) = schema.get("if") {
+            let branch = match self.is_valid(doc, condition, value, refs) {
+                true => schema.get("then"),
+                false => schema.get("else"),
+            };
+            if let Some(branch) = branch {
+                self.check(doc, branch, value, pointer, refs, errors);
+            }
+        }
+    }
+}
+
+fn load_document(path: &Path) -> Result<Value> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("schema: {} 文件不存在", path.display()))?;
+    let document: Value = serde_yaml::from_str(&text)
+        .with_context(|| format!("schema: {} 不是有效的 json schema", path.display()))?;
+    if !document.is_object() && !document.is_boolean() {
+        return Err(anyhow!("schema: {} 不是有效的 json schema", path.display()));
+    }
+    Ok(document)
+}
+
+/// the files named by the `$ref`s of a document
+fn collect_file_refs(value: &Value, files: &mut Vec<String>) {
+    match value {
+        Value::Object(obj) => {
+            if let Some(Value::String(reference)) = obj.get("$ref") {
+                let file = reference.split('#').next().unwrap_or_default();
+                if !file.is_empty() {
+                    files.push(file.to_string());
+                }
+            }
+            obj.values().for_each(|v| collect_file_refs(v, files));
+        }
+        Value::Array(items) => items.iter().for_each(|v| collect_file_refs(v, files)),
+        _ => {}
+    }
+}
+
+/// reject the keywords and formats this validator doesn't implement, and compile the patterns
+fn check_keywords(schema: &Value, patterns: &mut HashMap<String, Regex>) -> Result<()> {
+    let Value::Object(obj) = schema else {
+        return Ok(());
+    };
+    if let Some(keyword) = UNSUPPORTED_KEYWORDS.iter().find(|k| obj.contains_key(**k)) {
+        return Err(anyhow!("不支持 {} 关键字", keyword));
+    }
+    if let Some(format) = obj.get("format").and_then(|f| f.as_str()) {
+        if !FORMATS.contains(&format) {
+            return Err(anyhow!(
+                "不支持 format {}，只支持 {}",
+                format,
+                FORMATS.join(", ")
+            ));
+        }
+    }
+    let pattern = obj.get("pattern").and_then(|p| p.as_str());
+    let pattern_names = obj
+        .get("patternProperties")
+        .and_then(|p| p.as_object())
+        .into_iter()
+        .flat_map(|p| p.keys().map(String::as_str));
+    for pattern in pattern.into_iter().chain(pattern_names) {
+        if !patterns.contains_key(pattern) {
+            let regex = Regex::new(pattern)
+                .with_context(|| format!("pattern {} 不是有效的正则表达式", pattern))?;
+            patterns.insert(pattern.to_string(), regex);
+        }
+    }
+
+    for (key, value) in obj {
+        let children: Vec<&Value> = match value {
+            Value::Object(map) if SUBSCHEMA_MAP_KEYWORDS.contains(&key.as_str()) => {
+                map.values().collect()
+            }
+            Value::Array(list) if SUBSCHEMA_KEYWORDS.contains(&key.as_str()) => {
+                list.iter().collect()
+            }
+            value if SUBSCHEMA_KEYWORDS.contains(&key.as_str()) => vec![value],
+            _ => vec![],
+        };
+        for child in children {
+            check_keywords(child, patterns)?;
+        }
+    }
+    Ok(())
+}
+
+/// the formats of `FORMATS`, a string format always matches other types and the other way around
+fn matches_format(value: &Value, format: &str) -> bool {
+    let s = match (value, format) {
+        (Value::Number(n), "int32" | "int64") => {
+            let n = n.as_f64().unwrap_or(0.5);
+            let (min, max) = match format {
+                "int32" => (i32::MIN as f64, i32::MAX as f64),
+                _ => (i64::MIN as f64, i64::MAX as f64),
+            };
+            return n.fract() == 0.0 && (min..=max).contains(&n);
+        }
+        (Value::String(s), _) => s,
+        _ => return true,
+    };
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    let is_date = |s: &str| {
+        let parts: Vec<&str> = s.split('-').collect();
+        matches!(parts.as_slice(), [y, m, d] if y.len() == 4 && m.len() == 2 && d.len() == 2 && parts.iter().all(|p| digits(p)))
+    };
+    let is_time = |time: &str| {
+        let time = time.trim_end_matches(['Z', 'z']);
+        let time = time.split(['+', '-']).next().unwrap_or_default();
+        let hms: Vec<&str> = time
+            .split('.')
+            .next()
+            .unwrap_or_default()
+            .split(':')
+            .collect();
+        hms.len() == 3 && hms.iter().all(|p| p.len() == 2 && digits(p))
+    };
+    match format {
+        "date" => is_date(s),
+        "date-time" => match s.split_once(['T', 't', ' ']) {
+            Some((date, time)) => is_date(date) && is_time(time),
+            None => false,
+        },
+        "time" => is_time(s),
+        "email" => {
+            matches!(s.split_once('@'), Some((user, host)) if !user.is_empty() && host.contains('.'))
+        }
+        "hostname" => {
+            !s.is_empty()
+                && s.len() <= 253
+                && s.trim_end_matches('.').split('.').all(|label| {
+                    (1..=63).contains(&label.len())
+                        && !label.starts_with('-')
+                        && !label.ends_with('-')
+                        && label
+                            .bytes()
+                            .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+                })
+        }
+        "uuid" => {
+            let groups: Vec<&str> = s.split('-').collect();
+            groups.iter().map(|g| g.len()).eq([8, 4, 4, 4, 12])
+                && groups
+                    .iter()
+                    .all(|g| g.bytes().all(|b| b.is_ascii_hexdigit()))
+        }
+        "ipv4" => s.parse::<Ipv4Addr>().is_ok(),
+        "ipv6" => s.parse::<Ipv6Addr>().is_ok(),
+        "uri" | "url" => Url::parse(s).is_ok(),
+        "regex" => Regex::new(s).is_ok(),
+        "byte" => STANDARD.decode(s).is_ok(),
+        // binary, password, float and double don't restrict a string
+        _ => true,
+    }
+}
+
+fn error(pointer: &str, message: String) -> SchemaError {
+    SchemaError {
+        pointer: pointer.to_string(),
+        message,
+    }
+}
+
+fn is_type(value: &Value, ty: &str) -> bool {
+    match ty {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// escape a key as a JSON pointer segment
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn schema(root: Value) -> JsonSchema {
+        JsonSchema::new(root, HashMap::new()).unwrap()
+    }
+
+    /// the pointers of the violations
+    fn violations(root: Value, value: Value) -> Vec<String> {
+        schema(root)
+            .validate(&value)
+            .into_iter()
+            .map(|e| e.pointer)
+            .collect()
+    }
+
+    fn is_valid(root: Value, value: Value) -> bool {
+        schema(root).validate(&value).is_empty()
+    }
+
+    #[test]
+    fn ref_cycles_are_violations() {
+        let errors = schema(json!({ "$ref": "#" })).validate(&json!(1));
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains("cycle"));
+
+        let root = json!({
+            "$defs": { "a": { "$ref": "#/$defs/b" }, "b": { "$ref": "#/$defs/a" } },
+            "anyOf": [{ "$ref": "#/$defs/a" }],
+        });
+        assert!(!is_valid(root, json!({})));
+    }
+
+    #[test]
+    fn recursive_schemas_follow_the_value() {
+        let root = json!({
+            "type": "object",
+            "properties": {
+                "value": { "type": "integer" },
+                "children": { "type": "array", "items": { "$ref": "#" } },
+            },
+        });
+        let tree =
+            json!({ "value": 1, "children": [{ "value": 2, "children": [{ "value": "x" }] }] });
+        assert_eq!(violations(root, tree), ["/children/0/children/0/value"]);
+    }
+
+    #[test]
+    fn refs_to_other_files() {
+        let dir = std::env::temp_dir().join(format!("netdiff-schema-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("user.json"),
+            json!({ "$defs": { "id": { "type": "integer" } }, "properties": { "id": { "$ref": "#/$defs/id" } } })
+                .to_string(),
+        )
+        .unwrap();
+        std::fs::write(dir.join("loop.yml"), "$ref: loop.yml\n").unwrap();
+        std::fs::write(
+            dir.join("root.yml"),
+            "properties:\n  user:\n    $ref: user.json\n  loop:\n    $ref: loop.yml\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("missing.yml"), "$ref: nowhere.json\n").unwrap();
+
+        let root = JsonSchema::load(dir.join("root.yml").to_str().unwrap()).unwrap();
+        let errors = root.validate(&json!({ "user": { "id": "1" } }));
+        assert_eq!(errors[0].pointer, "/user/id");
+        let errors = root.validate(&json!({ "loop": 1 }));
+        assert!(errors[0].message.contains("cycle"));
+        assert!(JsonSchema::load(dir.join("missing.yml").to_str().unwrap()).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn type_enum_and_const() {
+        assert!(is_valid(json!({ "type": "integer" }), json!(1)));
+        assert!(is_valid(json!({ "type": "integer" }), json!(1.0)));
+        assert!(!is_valid(json!({ "type": "integer" }), json!(1.5)));
+        assert!(is_valid(json!({ "type": ["string", "null"] }), json!(null)));
+        assert!(!is_valid(json!({ "enum": [1, "a"] }), json!(2)));
+        assert!(!is_valid(json!({ "const": "a" }), json!("b")));
+        assert!(!is_valid(json!(false), json!(1)));
+        assert!(is_valid(json!(true), json!(1)));
+    }
+
+    #[test]
+    fn number_keywords() {
+        assert!(!is_valid(json!({ "minimum": 2 }), json!(1)));
+        assert!(!is_valid(json!({ "maximum": 2 }), json!(3)));
+        assert!(!is_valid(json!({ "exclusiveMinimum": 2 }), json!(2)));
+        assert!(!is_valid(json!({ "exclusiveMaximum": 2 }), json!(2)));
+        assert!(!is_valid(
+            json!({ "minimum": 2, "exclusiveMinimum": true }),
+            json!(2)
+        ));
+        assert!(is_valid(json!({ "multipleOf": 0.5 }), json!(1.5)));
+        assert!(!is_valid(json!({ "multipleOf": 2 }), json!(3)));
+    }
+
+    #[test]
+    fn string_keywords() {
+        assert!(!is_valid(json!({ "minLength": 2 }), json!("é")));
+        assert!(!is_valid(json!({ "maxLength": 1 }), json!("ab")));
+        for (format, good, bad) in [
+            ("date", "2024-01-31", "2024-1-31"),
+            ("date-time", "2024-01-31T10:00:00.5+08:00", "2024-01-31"),
+            ("email", "a@b.c", "a.b.c"),
+            ("uuid", "00000000-0000-0000-0000-000000000000", "0000"),
+            ("ipv4", "10.0.0.1", "10.0.0"),
+            ("ipv6", "::1", "1:::2"),
+            ("uri", "https://a.test", "a.test"),
+            ("time", "10:00:00Z", "10:00"),
+            ("hostname", "api.a-b.test", "-a.test"),
+            ("byte", "aGk=", "a"),
+            ("regex", "^a+$", "(a"),
+        ] {
+            assert!(
+                is_valid(json!({ "format": format }), json!(good)),
+                "{}",
+                format
+            );
+            assert!(
+                !is_valid(json!({ "format": format }), json!(bad)),
+                "{}",
+                format
+            );
+        }
+        assert!(!is_valid(json!({ "pattern": "^[a-z]+$" }), json!("ab1")));
+        assert!(is_valid(json!({ "pattern": "[0-9]" }), json!("ab1")));
+    }
+
+    #[test]
+    fn integer_formats() {
+        let int32 = json!({ "format": "int32" });
+        assert!(is_valid(int32.clone(), json!(2147483647)));
+        assert!(!is_valid(int32.clone(), json!(2147483648u64)));
+        assert!(!is_valid(int32.clone(), json!(1.5)));
+        assert!(is_valid(int32, json!("not a number")));
+        assert!(is_valid(json!({ "format": "int64" }), json!(-1)));
+    }
+
+    #[test]
+    fn unsupported_keywords_and_formats_are_rejected() {
+        let load = |root: Value| JsonSchema::new(root, HashMap::new());
+        assert!(load(json!({ "unevaluatedProperties": false })).is_err());
+        assert!(load(json!({ "items": [{ "unevaluatedItems": false }] })).is_err());
+        assert!(load(json!({ "$defs": { "a": { "format": "iri" } } })).is_err());
+        assert!(load(json!({ "pattern": "(a" })).is_err());
+        assert!(load(json!({ "patternProperties": { "(a": {} } })).is_err());
+        // names of properties are not keywords
+        assert!(load(json!({ "properties": { "format": { "format": "date" } } })).is_ok());
+    }
+
+    #[test]
+    fn array_keywords() {
+        assert!(!is_valid(json!({ "minItems": 2 }), json!([1])));
+        assert!(!is_valid(json!({ "maxItems": 1 }), json!([1, 2])));
+        assert!(!is_valid(json!({ "uniqueItems": true }), json!([1, 1])));
+        assert!(!is_valid(
+            json!({ "contains": { "const": 3 } }),
+            json!([1, 2])
+        ));
+        let twice = json!({ "contains": { "const": 1 }, "minContains": 2, "maxContains": 3 });
+        assert!(!is_valid(twice.clone(), json!([1, 2])));
+        assert!(is_valid(twice.clone(), json!([1, 1])));
+        assert!(!is_valid(twice, json!([1, 1, 1, 1])));
+        let items = json!({ "items": { "type": "string" } });
+        assert_eq!(violations(items, json!(["a", 1])), ["/1"]);
+        let prefix = json!({ "prefixItems": [{ "type": "string" }], "items": false });
+        assert_eq!(violations(prefix, json!(["a", 1])), ["/1"]);
+        let tuple =
+            json!({ "items": [{ "type": "string" }], "additionalItems": { "type": "string" } });
+        assert_eq!(violations(tuple, json!(["a", 1])), ["/1"]);
+    }
+
+    #[test]
+    fn object_keywords() {
+        assert!(!is_valid(json!({ "minProperties": 1 }), json!({})));
+        assert!(!is_valid(json!({ "maxProperties": 0 }), json!({ "a": 1 })));
+        assert!(!is_valid(json!({ "required": ["a"] }), json!({})));
+        assert!(!is_valid(
+            json!({ "dependentRequired": { "a": ["b"] } }),
+            json!({ "a": 1 })
+        ));
+        assert!(!is_valid(
+            json!({ "propertyNames": { "maxLength": 1 } }),
+            json!({ "ab": 1 })
+        ));
+        let closed = json!({ "properties": { "a": {} }, "additionalProperties": false });
+        assert_eq!(violations(closed, json!({ "a": 1, "b/c": 2 })), ["/b~1c"]);
+        let extra = json!({ "additionalProperties": { "type": "integer" } });
+        assert_eq!(violations(extra, json!({ "a": "x" })), ["/a"]);
+        let patterns = json!({
+            "properties": { "id": { "type": "integer" } },
+            "patternProperties": { "^x-": { "type": "string" }, "d$": { "minLength": 2 } },
+            "additionalProperties": false,
+        });
+        assert_eq!(
+            violations(patterns, json!({ "id": 1, "x-id": 1, "x-d": "a", "y": 1 })),
+            ["/x-d", "/x-id", "/y"]
+        );
+        let dependent = json!({ "dependentSchemas": { "a": { "required": ["b"] } } });
+        assert!(!is_valid(dependent.clone(), json!({ "a": 1 })));
+        assert!(is_valid(dependent, json!({ "c": 1 })));
+        let draft7 = json!({ "dependencies": { "a": ["b"], "c": { "maxProperties": 1 } } });
+        assert!(!is_valid(draft7.clone(), json!({ "a": 1 })));
+        assert!(!is_valid(draft7.clone(), json!({ "c": 1, "d": 1 })));
+        assert!(is_valid(draft7, json!({ "a": 1, "b": 1 })));
+    }
+
+    #[test]
+    fn combinators() {
+        let ints = [json!({ "type": "integer" }), json!({ "minimum": 0 })];
+        assert!(!is_valid(json!({ "allOf": ints }), json!(-1)));
+        assert!(is_valid(json!({ "anyOf": ints }), json!(-1)));
+        assert!(!is_valid(json!({ "anyOf": ints }), json!(-1.5)));
+        assert!(!is_valid(json!({ "oneOf": ints }), json!(1)));
+        assert!(is_valid(json!({ "oneOf": ints }), json!(0.5)));
+        assert!(!is_valid(json!({ "not": { "type": "null" } }), json!(null)));
+        let branch = json!({
+            "if": { "type": "string" },
+            "then": { "minLength": 2 },
+            "else": { "type": "integer" },
+        });
+        assert!(!is_valid(branch.clone(), json!("a")));
+        assert!(!is_valid(branch.clone(), json!(1.5)));
+        assert!(is_valid(branch, json!(1)));
+    }
+
+    #[test]
+    fn a_body_that_is_not_json_is_one_violation() {
+        assert_eq!(schema(json!({})).validate_text("<html>").len(), 1);
+    }
+}
@@ -0,0 +1,294 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use reqwest::Response;
+use serde::{Deserialize, Serialize};
+use tokio::time::{timeout_at, Instant};
+
+use super::filter_json;
+
+/// how long events are collected when neither `duration` nor `count` is set
+const DEFAULT_DURATION: u64 = 10_000;
+
+/// how the events of a `text/event-stream` response are collected
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseProfile {
+    /// milliseconds to collect events for
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub duration: Option<u64>,
+    /// events to collect before the stream is closed
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub count: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    pub event: Option<String>,
+    pub id: Option<String>,
+    pub data: String,
+}
+
+impl SseProfile {
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.duration == Some(0) || self.count == Some(0) {
+            return Err(anyhow!("sse: duration 和 count 必须大于 0"));
+        }
+        Ok(())
+    }
+
+    /// events until the stream ends, `count` is reached, `duration` is over or `limit` bytes
+    /// are read, the bytes read and whether the stream was cut off by `limit` come along
+    pub(super) async fn collect(
+        &self,
+        res: &mut Response,
+        limit: Option<u64>,
+    ) -> Result<(Vec<SseEvent>, u64, bool)> {
+        let duration = match (self.duration, self.count) {
+            (None, None) => Some(DEFAULT_DURATION),
+            (duration, _) => duration,
+        };
+        let deadline = duration.map(|ms| Instant::now() + Duration::from_millis(ms));
+        let mut parser = SseParser::default();
+        let mut events = vec![];
+        let mut size = 0;
+        loop {
+            let chunk = match deadline {
+                Some(deadline) => match timeout_at(deadline, res.chunk()).await {
+                    Ok(chunk) => chunk?,
+                    Err(_) => break,
+                },
+                None => res.chunk().await?,
+            };
+            let Some(chunk) = chunk else {
+                events.extend(parser.finish());
+                break;
+            };
+            let room = limit.map_or(u64::MAX, |limit| limit - size);
+            if chunk.len() as u64 > room {
+                // the event cut off by the limit is dropped, the ones before it are whole
+                events.extend(parser.feed(&chunk[..room as usize]));
+                size += room;
+                events.truncate(self.count.unwrap_or(usize::MAX));
+                return Ok((events, size, true));
+            }
+            size += chunk.len() as u64;
+            events.extend(parser.feed(&chunk));
+            if let Some(count) = self.count {
+                if events.len() >= count {
+                    events.truncate(count);
+                    break;
+                }
+            }
+        }
+        Ok((events, size, false))
+    }
+}
+
+impl SseEvent {
+    /// the event the way it goes over the wire
+    pub fn to_wire(&self) -> String {
+        let mut text = String::new();
+        if let Some(event) = &self.event {
+            text.push_str(&format!("event: {}\n", event));
+        }
+        if let Some(id) = &self.id {
+            text.push_str(&format!("id: {}\n", id));
+        }
+        for line in self.data.split('\n') {
+            text.push_str(&format!("data: {}\n", line));
+        }
+        text.push('\n');
+        text
+    }
+}
+
+/// every event of an event stream, json data filtered like `filter_json`, a blank line between
+pub fn filter_sse(text: &str, skip: &[String]) -> Result<String> {
+    let mut events = vec![];
+    for event in parse_sse(text) {
+        let mut lines = vec![];
+        if let Some(name) = &event.event {
+            lines.push(format!("event: {}", name));
+        }
+        if let Some(id) = &event.id {
+            lines.push(format!("id: {}", id));
+        }
+        match filter_json(&event.data, skip) {
+            Ok(data) => lines.push(data),
+            Err(_) => lines.push(event.data),
+        }
+        events.push(lines.join("\n"));
+    }
+    Ok(events.join("\n\n"))
+}
+
+pub fn parse_sse(text: &str) -> Vec<SseEvent> {
+    let mut parser = SseParser::default();
+    let mut events = parser.feed(text.as_bytes());
+    events.extend(parser.finish());
+    events
+}
+
+/// turns chunks of an event stream into events, a line can be split across chunks
+#[derive(Default)]
+struct SseParser {
+    buf: Vec<u8>,
+    event: Option<String>,
+    id: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buf.extend_from_slice(chunk);
+        let mut events = vec![];
+        while let Some(end) = self.buf.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            if let Some(event) = self.line(line.trim_end_matches(['\n', '\r'])) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// a stream that ends without a blank line still dispatches its last event
+    fn finish(&mut self) -> Option<SseEvent> {
+        let rest = String::from_utf8_lossy(&std::mem::take(&mut self.buf)).into_owned();
+        if !rest.is_empty() {
+            self.line(rest.trim_end_matches('\r'));
+        }
+        self.line("")
+    }
+
+    fn line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            let event = self.event.take();
+            let data = std::mem::take(&mut self.data);
+            // events without data are not dispatched, their id still counts
+            return (!data.is_empty()).then(|| SseEvent {
+                event,
+                id: self.id.clone(),
+                data: data.join("\n"),
+            });
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "id" => self.id = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            _ => {}
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(event: Option<&str>, id: Option<&str>, data: &str) -> SseEvent {
+        SseEvent {
+            event: event.map(String::from),
+            id: id.map(String::from),
+            data: data.to_string(),
+        }
+    }
+
+    fn response(body: &'static str) -> Response {
+        http::Response::builder()
+            .header("content-type", "text/event-stream")
+            .body(body)
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn a_line_split_across_chunks() {
+        let mut parser = SseParser::default();
+        assert!(parser.feed(b"event: up").is_empty());
+        assert!(parser.feed(b"date\nda").is_empty());
+        assert_eq!(
+            parser.feed(b"ta: {\"a\": 1}\n\n"),
+            [event(Some("update"), None, "{\"a\": 1}")]
+        );
+        assert_eq!(parser.finish(), None);
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        let mut parser = SseParser::default();
+        assert_eq!(parser.feed(b"id: 7\r\ndata: a\r\n\r"), []);
+        assert_eq!(parser.feed(b"\n"), [event(None, Some("7"), "a")]);
+    }
+
+    #[test]
+    fn data_lines_are_joined() {
+        assert_eq!(
+            parse_sse("data: a\ndata\ndata:  b\n: comment\n\n"),
+            [event(None, None, "a\n\n b")]
+        );
+    }
+
+    #[test]
+    fn an_event_without_data_is_not_dispatched() {
+        assert_eq!(
+            parse_sse("event: ping\nid: 1\n\ndata: x\n\n"),
+            [event(None, Some("1"), "x")]
+        );
+    }
+
+    #[test]
+    fn a_stream_without_a_final_blank_line() {
+        assert_eq!(
+            parse_sse("data: a\n\ndata: b"),
+            [event(None, None, "a"), event(None, None, "b")]
+        );
+        assert_eq!(parse_sse("data: a\r\n"), [event(None, None, "a")]);
+    }
+
+    #[test]
+    fn events_go_back_on_the_wire_as_they_came() {
+        let e = event(Some("update"), Some("2"), "a\nb");
+        assert_eq!(e.to_wire(), "event: update\nid: 2\ndata: a\ndata: b\n\n");
+        assert_eq!(parse_sse(&e.to_wire()), [e]);
+    }
+
+    #[test]
+    fn json_data_is_filtered() {
+        let text = "event: a\ndata: {\"id\": 1, \"at\": 2}\n\ndata: plain\n\n";
+        assert_eq!(
+            filter_sse(text, &["at".to_string()]).unwrap(),
+            "event: a\n{\n  \"id\": 1\n}\n\nplain"
+        );
+    }
+
+    #[tokio::test]
+    async fn collecting_stops_at_count() {
+        let profile = SseProfile {
+            count: Some(1),
+            ..SseProfile::default()
+        };
+        let mut res = response("data: a\n\ndata: b\n\n");
+        let (events, size, truncated) = profile.collect(&mut res, None).await.unwrap();
+        assert_eq!(events, [event(None, None, "a")]);
+        assert_eq!((size, truncated), (18, false));
+    }
+
+    #[tokio::test]
+    async fn collecting_stops_at_the_body_limit() {
+        let mut res = response("data: a\n\ndata: b\n\n");
+        let (events, size, truncated) = SseProfile::default()
+            .collect(&mut res, Some(12))
+            .await
+            .unwrap();
+        assert_eq!(events, [event(None, None, "a")]);
+        assert_eq!((size, truncated), (12, true));
+    }
+}
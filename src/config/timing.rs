@@ -0,0 +1,323 @@
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use rustls::client::{
+    ClientSessionMemoryCache, ClientSessionStore, Tls12ClientSessionValue, Tls13ClientSessionValue,
+};
+use rustls::{KeyLog, NamedGroup, ServerName};
+use tokio::net::lookup_host;
+
+/// sessions kept for resumption, the rustls default
+const SESSION_CACHE_SIZE: usize = 256;
+/// logged once the tls 1.3 handshake is done
+const CLIENT_TRAFFIC_SECRET: &str = "CLIENT_TRAFFIC_SECRET_0";
+
+/// where the time of a request went, the connection phases are those of the last connection
+/// the request opened
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timing {
+    /// looking up the host, `None` for ip hosts, `resolve` entries and reused connections
+    pub dns: Option<Duration>,
+    /// the tcp connect, `None` for plain http where nothing marks its end, and reused connections
+    pub connect: Option<Duration>,
+    /// the tls handshake, `None` for plain http, reused connections and tls 1.2 servers that
+    /// hand out no session
+    pub tls: Option<Duration>,
+    /// from sending the request to the response headers
+    pub ttfb: Duration,
+    /// from sending the request to the end of the body
+    pub total: Duration,
+    /// bytes of the body that were read
+    pub size: u64,
+}
+
+impl Timing {
+    /// a row for each phase and the size, with req2 as a multiple of req1
+    pub fn compare(&self, other: &Timing) -> Result<String> {
+        let mut output = String::new();
+        writeln!(output, "{:<8} {:>10} {:>10}", "", "req1", "req2")?;
+        let phases = [
+            ("dns", self.dns, other.dns),
+            ("connect", self.connect, other.connect),
+            ("tls", self.tls, other.tls),
+            ("ttfb", Some(self.ttfb), Some(other.ttfb)),
+            ("total", Some(self.total), Some(other.total)),
+        ];
+        for (name, d1, d2) in phases {
+            if d1.is_none() && d2.is_none() {
+                continue;
+            }
+            let ratio = match (d1, d2) {
+                (Some(d1), Some(d2)) => ratio_text(d1.as_secs_f64(), d2.as_secs_f64()),
+                _ => String::new(),
+            };
+            writeln!(
+                output,
+                "{:<8} {:>10} {:>10} {}",
+                name,
+                duration_text(d1),
+                duration_text(d2),
+                ratio
+            )?;
+        }
+        writeln!(
+            output,
+            "{:<8} {:>10} {:>10} {}",
+            "size",
+            format!("{}B", self.size),
+            format!("{}B", other.size),
+            ratio_text(self.size as f64, other.size as f64)
+        )?;
+        Ok(output
+            .lines()
+            .map(str::trim_end)
+            .collect::<Vec<_>>()
+            .join("\n")
+            + "\n")
+    }
+}
+
+fn duration_text(d: Option<Duration>) -> String {
+    match d {
+        Some(d) => format!("{:.1}ms", d.as_secs_f64() * 1000.0),
+        None => "-".to_string(),
+    }
+}
+
+fn ratio_text(a: f64, b: f64) -> String {
+    if a > 0.0 {
+        format!("x{:.2}", b / a)
+    } else {
+        String::new()
+    }
+}
+
+/// what the resolver and the tls hooks saw during an attempt
+#[derive(Debug, Default)]
+struct Phases {
+    lookup: Option<(Instant, Instant)>,
+    handshake_start: Option<Instant>,
+    handshake_end: Option<Instant>,
+}
+
+/// times the connection phases of a request, dns as the system resolver of the client, the tls
+/// handshake as the session store and key log of its rustls config
+#[derive(Clone)]
+pub(super) struct ConnectionTimer {
+    phases: Arc<Mutex<Phases>>,
+    sessions: Arc<ClientSessionMemoryCache>,
+}
+
+impl Default for ConnectionTimer {
+    fn default() -> Self {
+        Self {
+            phases: Arc::default(),
+            sessions: Arc::new(ClientSessionMemoryCache::new(SESSION_CACHE_SIZE)),
+        }
+    }
+}
+
+impl ConnectionTimer {
+    /// forget the phases of a previous attempt
+    pub(super) fn reset(&self) {
+        *self.phases.lock().unwrap() = Phases::default();
+    }
+
+    /// the phases of the attempt started at `start`, the tcp connect runs from the end of the
+    /// lookup, or the start for a host that isn't looked up, to the start of the handshake
+    pub(super) fn timing(&self, start: Instant, ttfb: Duration) -> Timing {
+        let phases = self.phases.lock().unwrap();
+        let connect_start = phases.lookup.map_or(start, |(_, end)| end);
+        let handshake_start = phases.handshake_start;
+        Timing {
+            dns: phases.lookup.map(|(start, end)| end - start),
+            connect: handshake_start.and_then(|s| s.checked_duration_since(connect_start)),
+            tls: phases
+                .handshake_end
+                .zip(handshake_start)
+                .and_then(|(end, start)| end.checked_duration_since(start)),
+            ttfb,
+            ..Timing::default()
+        }
+    }
+
+    fn handshake_started(&self) {
+        let mut phases = self.phases.lock().unwrap();
+        phases.handshake_start = Some(Instant::now());
+        phases.handshake_end = None;
+    }
+
+    fn handshake_done(&self) {
+        let mut phases = self.phases.lock().unwrap();
+        if phases.handshake_end.is_none() {
+            phases.handshake_end = Some(Instant::now());
+        }
+    }
+}
+
+impl Resolve for ConnectionTimer {
+    fn resolve(&self, name: Name) -> Resolving {
+        let phases = self.phases.clone();
+        Box::pin(async move {
+            let start = Instant::now();
+            let addrs: Vec<SocketAddr> = lookup_host((name.as_str(), 0)).await?.collect();
+            phases.lock().unwrap().lookup = Some((start, Instant::now()));
+            Result::Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// rustls looks up a ticket to resume as it starts the handshake, and saves a tls 1.2 session
+/// on the server's finished message
+impl ClientSessionStore for ConnectionTimer {
+    fn set_kx_hint(&self, server_name: &ServerName, group: NamedGroup) {
+        self.sessions.set_kx_hint(server_name, group);
+    }
+
+    fn kx_hint(&self, server_name: &ServerName) -> Option<NamedGroup> {
+        self.sessions.kx_hint(server_name)
+    }
+
+    fn set_tls12_session(&self, server_name: &ServerName, value: Tls12ClientSessionValue) {
+        self.handshake_done();
+        self.sessions.set_tls12_session(server_name, value);
+    }
+
+    fn tls12_session(&self, server_name: &ServerName) -> Option<Tls12ClientSessionValue> {
+        self.sessions.tls12_session(server_name)
+    }
+
+    fn remove_tls12_session(&self, server_name: &ServerName) {
+        self.sessions.remove_tls12_session(server_name);
+    }
+
+    fn insert_tls13_ticket(&self, server_name: &ServerName, value: Tls13ClientSessionValue) {
+        self.sessions.insert_tls13_ticket(server_name, value);
+    }
+
+    fn take_tls13_ticket(&self, server_name: &ServerName) -> Option<Tls13ClientSessionValue> {
+        self.handshake_started();
+        self.sessions.take_tls13_ticket(server_name)
+    }
+}
+
+/// the client traffic secret is derived on the server's finished message
+impl KeyLog for ConnectionTimer {
+    fn log(&self, label: &str, _client_random: &[u8], _secret: &[u8]) {
+        if label == CLIENT_TRAFFIC_SECRET {
+            self.handshake_done();
+        }
+    }
+
+    fn will_log(&self, label: &str) -> bool {
+        label == CLIENT_TRAFFIC_SECRET
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn compare_shows_req2_as_a_multiple_of_req1() {
+        let timing1 = Timing {
+            dns: Some(ms(2)),
+            connect: Some(ms(10)),
+            tls: Some(ms(20)),
+            ttfb: ms(100),
+            total: ms(150),
+            size: 1000,
+        };
+        let timing2 = Timing {
+            dns: None,
+            connect: Some(ms(5)),
+            tls: Some(ms(30)),
+            ttfb: ms(250),
+            total: ms(300),
+            size: 0,
+        };
+        assert_eq!(
+            timing1.compare(&timing2).unwrap(),
+            [
+                "               req1       req2",
+                "dns           2.0ms          -",
+                "connect      10.0ms      5.0ms x0.50",
+                "tls          20.0ms     30.0ms x1.50",
+                "ttfb        100.0ms    250.0ms x2.50",
+                "total       150.0ms    300.0ms x2.00",
+                "size          1000B         0B x0.00",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn phases_missing_on_both_sides_are_left_out() {
+        let timing = Timing {
+            ttfb: ms(1),
+            total: ms(2),
+            ..Timing::default()
+        };
+        let table = timing.compare(&timing).unwrap();
+        let rows: Vec<&str> = table
+            .lines()
+            .skip(1)
+            .map(|l| l.split_whitespace().next().unwrap())
+            .collect();
+        assert_eq!(rows, ["ttfb", "total", "size"]);
+        // nothing to divide by
+        assert!(
+            table.ends_with("size             0B         0B\n"),
+            "{}",
+            table
+        );
+    }
+
+    #[test]
+    fn the_connect_runs_from_the_lookup_to_the_handshake() {
+        let timer = ConnectionTimer::default();
+        let start = Instant::now();
+        *timer.phases.lock().unwrap() = Phases {
+            lookup: Some((start + ms(1), start + ms(3))),
+            handshake_start: Some(start + ms(10)),
+            handshake_end: Some(start + ms(25)),
+        };
+        let timing = timer.timing(start, ms(40));
+        assert_eq!(timing.dns, Some(ms(2)));
+        assert_eq!(timing.connect, Some(ms(7)));
+        assert_eq!(timing.tls, Some(ms(15)));
+        assert_eq!(timing.ttfb, ms(40));
+
+        // no lookup for an ip host, the connect starts with the attempt
+        timer.phases.lock().unwrap().lookup = None;
+        assert_eq!(timer.timing(start, ms(40)).connect, Some(ms(10)));
+
+        timer.reset();
+        let timing = timer.timing(start, ms(40));
+        assert_eq!((timing.dns, timing.connect, timing.tls), (None, None, None));
+    }
+
+    #[test]
+    fn a_new_handshake_drops_the_end_of_the_previous_one() {
+        let timer = ConnectionTimer::default();
+        timer.handshake_started();
+        timer.log(CLIENT_TRAFFIC_SECRET, &[], &[]);
+        assert!(timer.phases.lock().unwrap().handshake_end.is_some());
+        assert!(!timer.will_log("CLIENT_RANDOM"));
+
+        timer.handshake_started();
+        let timing = timer.timing(Instant::now(), ms(1));
+        assert_eq!(timing.tls, None);
+        assert!(timing.connect.is_none());
+    }
+}
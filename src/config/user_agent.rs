@@ -0,0 +1,100 @@
+/// named user agents that can be used in place of a full user agent string
+pub const USER_AGENT_PRESETS: &[(&str, &str)] = &[
+    (
+        "chrome",
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36",
+    ),
+    (
+        "firefox",
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:125.0) Gecko/20100101 Firefox/125.0",
+    ),
+    (
+        "safari",
+        "Mozilla/5.0 (Macintosh; Intel Mac OS X 14_4_1) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4.1 Safari/605.1.15",
+    ),
+    (
+        "edge",
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36 Edg/124.0.0.0",
+    ),
+    (
+        "iphone",
+        "Mozilla/5.0 (iPhone; CPU iPhone OS 17_4_1 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4.1 Mobile/15E148 Safari/604.1",
+    ),
+    (
+        "android",
+        "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Mobile Safari/537.36",
+    ),
+    (
+        "googlebot",
+        "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)",
+    ),
+    (
+        "googlebot-mobile",
+        "Mozilla/5.0 (Linux; Android 6.0.1; Nexus 5X Build/MMB29P) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Mobile Safari/537.36 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)",
+    ),
+    (
+        "bingbot",
+        "Mozilla/5.0 (compatible; bingbot/2.0; +http://www.bing.com/bingbot.htm)",
+    ),
+    (
+        "curl",
+        "curl/8.7.1",
+    ),
+];
+
+/// expand a preset name, any other value is used as is
+pub fn resolve_user_agent(name: &str) -> &str {
+    USER_AGENT_PRESETS
+        .iter()
+        .find(|(preset, _)| preset.eq_ignore_ascii_case(name))
+        .map(|(_, ua)| *ua)
+        .unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use http::header::USER_AGENT;
+
+    use super::*;
+    use crate::{ExtraArgs, LoadConfig, RequestConfig};
+
+    #[test]
+    fn presets_expand_ignoring_case() {
+        assert_eq!(resolve_user_agent("curl"), "curl/8.7.1");
+        assert!(resolve_user_agent("GoogleBot").contains("Googlebot/2.1"));
+        assert_eq!(resolve_user_agent("my-client/1.0"), "my-client/1.0");
+    }
+
+    #[test]
+    fn preset_names_are_unique() {
+        let mut names: Vec<&str> = USER_AGENT_PRESETS.iter().map(|(name, _)| *name).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), USER_AGENT_PRESETS.len());
+    }
+
+    #[tokio::test]
+    async fn the_cli_overrides_the_profile_which_overrides_the_defaults() {
+        let config = RequestConfig::from_yaml(
+            "defaults:\n  user_agent: firefox\nown:\n  url: http://a.test/\n  user_agent: curl\n  headers:\n    user-agent: header\ninherited:\n  url: http://a.test/\n",
+        )
+        .unwrap();
+        let user_agent = |name: &str, extra: ExtraArgs| {
+            let profile = config.get_profile(name).unwrap().clone();
+            async move {
+                let req = profile.prepare_request(&extra).await.unwrap();
+                req.headers()[USER_AGENT].to_str().unwrap().to_string()
+            }
+        };
+        assert_eq!(user_agent("own", ExtraArgs::default()).await, "curl/8.7.1");
+        assert_eq!(
+            user_agent("inherited", ExtraArgs::default()).await,
+            resolve_user_agent("firefox")
+        );
+        let extra = ExtraArgs {
+            user_agent: Some("bingbot".to_string()),
+            ..ExtraArgs::default()
+        };
+        assert!(user_agent("own", extra).await.contains("bingbot/2.0"));
+    }
+}
@@ -0,0 +1,138 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use futures_util::{SinkExt, StreamExt};
+use http::{header::USER_AGENT, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::time::timeout;
+use tokio_tungstenite::tungstenite::{client::IntoClientRequest, Message};
+
+use super::{filter_json, resolve_user_agent, RequestProfile};
+use crate::ExtraArgs;
+
+/// milliseconds to wait for the next message when neither `timeout` nor `count` is set
+const DEFAULT_TIMEOUT: u64 = 1000;
+
+/// a scripted websocket conversation, `url` may be `ws(s)://` or `http(s)://`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct WebsocketProfile {
+    /// messages sent in order once the socket is open, strings as is, anything else as json
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub send: Vec<Value>,
+    /// milliseconds to wait for the next message before the socket is closed
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub timeout: Option<u64>,
+    /// messages to collect before the socket is closed
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub count: Option<usize>,
+}
+
+impl WebsocketProfile {
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.timeout == Some(0) || self.count == Some(0) {
+            return Err(anyhow!("websocket: timeout 和 count 必须大于 0"));
+        }
+        Ok(())
+    }
+}
+
+impl RequestProfile {
+    /// the handshake status followed by the messages sent (`>`) and received (`<`) in order,
+    /// json messages filtered like `filter_json`
+    pub async fn converse(&self, extra_args: &ExtraArgs, skip: &[String]) -> Result<String> {
+        let ws = self
+            .websocket
+            .as_ref()
+            .ok_or_else(|| anyhow!("{} is not a websocket profile", self.url))?;
+        let mut url = self.get_url(extra_args)?.parse::<url::Url>()?;
+        let scheme = match url.scheme() {
+            "http" => "ws",
+            "https" => "wss",
+            scheme => scheme,
+        }
+        .to_string();
+        url.set_scheme(&scheme)
+            .map_err(|_| anyhow!("{} 不是有效的 websocket url", url))?;
+
+        let mut request = url.as_str().into_client_request()?;
+        let headers = request.headers_mut();
+        for (k, v) in &self.headers {
+            headers.insert(k, v.clone());
+        }
+        for (k, v) in &extra_args.headers {
+            headers.insert(
+                HeaderName::from_bytes(k.as_bytes())?,
+                HeaderValue::from_str(v)?,
+            );
+        }
+        let user_agent = extra_args.user_agent.as_ref().or(self.user_agent.as_ref());
+        if let Some(user_agent) = user_agent {
+            headers.insert(
+                USER_AGENT,
+                HeaderValue::from_str(resolve_user_agent(user_agent))?,
+            );
+        }
+
+        let connect = tokio_tungstenite::connect_async(request);
+        let (mut socket, res) = match self.retry.timeout() {
+            Some(limit) => timeout(limit, connect)
+                .await
+                .map_err(|_| anyhow!("{} timed out", url))?,
+            None => connect.await,
+        }
+        .with_context(|| format!("websocket {} failed", url))?;
+
+        let mut output = String::new();
+        writeln!(output, "HTTP/1.1 {}", res.status())?;
+        for value in &ws.send {
+            let text = match value {
+                Value::String(s) => s.clone(),
+                v => v.to_string(),
+            };
+            writeln!(output, "> {}", indent(&message_text(&text, skip)))?;
+            socket.send(Message::Text(text)).await?;
+        }
+
+        let wait = Duration::from_millis(match (ws.timeout, ws.count) {
+            (None, None) => DEFAULT_TIMEOUT,
+            (timeout, _) => timeout.unwrap_or(u64::MAX),
+        });
+        let mut received = 0;
+        while ws.count.is_none_or(|count| received < count) {
+            let message = match timeout(wait, socket.next()).await {
+                Ok(Some(message)) => message?,
+                Ok(None) | Err(_) => break,
+            };
+            match message {
+                Message::Text(text) => {
+                    writeln!(output, "< {}", indent(&message_text(&text, skip)))?
+                }
+                Message::Binary(bytes) => writeln!(output, "< binary {} bytes", bytes.len())?,
+                Message::Close(frame) => {
+                    match frame {
+                        Some(frame) => writeln!(output, "closed: {} {}", frame.code, frame.reason)?,
+                        None => writeln!(output, "closed")?,
+                    }
+                    break;
+                }
+                // pings are answered by the socket itself
+                Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => continue,
+            }
+            received += 1;
+        }
+        // the server may be gone already
+        let _ = socket.close(None).await;
+        Ok(output)
+    }
+}
+
+fn message_text(text: &str, skip: &[String]) -> String {
+    filter_json(text, skip).unwrap_or_else(|_| text.to_string())
+}
+
+/// continuation lines of a multi-line message line up after the `> ` marker
+fn indent(text: &str) -> String {
+    text.replace('\n', "\n  ")
+}
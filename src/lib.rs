@@ -3,13 +3,25 @@ mod utils;
 
 pub mod cli;
 pub use config::{
-    get_body_text, get_header_text, get_status_text, is_default, DiffConfig, DiffProfile,
-    LoadConfig, RequestConfig, RequestProfile, ResponseProfile,
+    binary_summary, diff_binary, filter_sse, get_body_text, get_header_text, get_redirect_text,
+    get_status_text, is_binary, is_default, parse_collection, parse_curl_args, parse_curl_commands,
+    parse_har, parse_openapi, parse_resolve, parse_sse, resolve_user_agent, retarget_url,
+    split_graphql, Backoff, BodyFormat, CollectionVars, DiffConfig, DiffProfile, DiffResult,
+    Expect, FollowRedirects, GraphqlRequest, GraphqlText, GrpcRequest, HarFilter, JsonSchema,
+    LoadConfig, MultipartPart, ProtobufMessage, RedirectHop, RepeatResult, RequestBody,
+    RequestConfig, RequestDefaults, RequestProfile, ResolveEntry, ResponseProfile, ResponseReport,
+    RetryError, RetryOn, RetryProfile, RouteProfile, SchemaError, SseEvent, SseProfile,
+    StatusExpect, StatusRange, Timing, TlsProfile, Variance, WebsocketProfile, USER_AGENT_PRESETS,
+};
+pub use utils::{
+    diff_text_to_terminal_inline, handle_run_err, highlight_text, print_failures, print_yaml,
 };
-pub use utils::{diff_text_to_terminal_inline, handle_run_err, highlight_text};
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct ExtraArgs {
     pub headers: Vec<(String, String)>,
     pub body: Vec<(String, String)>,
     pub query: Vec<(String, String)>,
+    /// `(field, path)` of the files sent as multipart parts
+    pub files: Vec<(String, String)>,
+    pub user_agent: Option<String>,
 }
@@ -1,11 +1,12 @@
 use anyhow::{Ok, Result};
 use atty::Stream;
 use console::{style, Style};
+use serde::Serialize;
 use similar::{ChangeTag, TextDiff};
 use std::fmt;
 use std::fmt::Write as _;
-use std::io::stderr;
 use std::io::Write as _;
+use std::io::{stderr, stdout};
 use syntect::easy::HighlightLines;
 use syntect::highlighting::ThemeSet;
 use syntect::parsing::SyntaxSet;
@@ -70,10 +71,7 @@ pub fn highlight_text(text: &str, extensions: &str, theme: Option<&str>) -> Resu
         ps.find_syntax_plain_text()
     };
 
-    let mut h = HighlightLines::new(
-        syntax,
-        &ts.themes[theme.unwrap_or_else(|| "base16-ocean.dark")],
-    );
+    let mut h = HighlightLines::new(syntax, &ts.themes[theme.unwrap_or("base16-ocean.dark")]);
     let mut output = String::new();
     for line in LinesWithEndings::from(text) {
         let ranges = h.highlight_line(line, &ps).unwrap();
@@ -83,19 +81,54 @@ pub fn highlight_text(text: &str, extensions: &str, theme: Option<&str>) -> Resu
     Ok(output)
 }
 
+/// print a config as yaml, highlighted when stdout is a terminal
+pub fn print_yaml<T: Serialize>(config: &T) -> Result<()> {
+    let result = serde_yaml::to_string(config)?;
+    let mut stdout = stdout().lock();
+
+    if atty::is(Stream::Stdout) {
+        write!(
+            stdout,
+            "======== Parse Yaml ========\n{}",
+            highlight_text(&result, "yaml", None)?
+        )?;
+    } else {
+        write!(stdout, "{}", &result)?;
+    }
+
+    Ok(())
+}
+
+/// print the failed expectations in their own section, returns an error if there is any
+pub fn print_failures(failures: &[String]) -> Result<()> {
+    if failures.is_empty() {
+        return Ok(());
+    }
+    let mut stdout = stdout().lock();
+    writeln!(stdout, "======== expect ========")?;
+    for failure in failures {
+        if atty::is(Stream::Stdout) {
+            writeln!(stdout, "{}", Style::new().red().apply_to(failure))?;
+        } else {
+            writeln!(stdout, "{}", failure)?;
+        }
+    }
+    Err(anyhow::anyhow!("{} expectation(s) failed", failures.len()))
+}
+
+/// print the error and exit with status 1
 pub fn handle_run_err(result: Result<()>) -> Result<()> {
-    match result {
-        Err(e) => {
-            let stderr = stderr();
-            let mut stderr = stderr.lock();
-            if atty::is(Stream::Stderr) {
-                let s = Style::new().red();
-                write!(stderr, "{:?}", s.apply_to(format!("{:?}", e)))?;
-            } else {
-                write!(stderr, "{:?}", e)?;
-            }
+    if let Err(e) = result {
+        let stderr = stderr();
+        let mut stderr = stderr.lock();
+        if atty::is(Stream::Stderr) {
+            let s = Style::new().red();
+            write!(stderr, "{:?}", s.apply_to(format!("{:?}", e)))?;
+        } else {
+            write!(stderr, "{:?}", e)?;
         }
-        _ => {}
+        drop(stderr);
+        std::process::exit(1);
     }
 
     Ok(())
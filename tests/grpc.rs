@@ -0,0 +1,182 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+use anyhow::Result;
+use hyper::{
+    body::HttpBody,
+    service::{make_service_fn, service_fn},
+    Body, HeaderMap, Request, Response, Server,
+};
+use netdiff::{DiffConfig, ExtraArgs, LoadConfig, RequestConfig, ResponseProfile};
+use prost::Message;
+use prost_types::{
+    field_descriptor_proto::{Label, Type},
+    DescriptorProto, FieldDescriptorProto, FileDescriptorProto, FileDescriptorSet,
+    MethodDescriptorProto, ServiceDescriptorProto,
+};
+
+/// `echo.Echo/Say` taking and returning `echo.Ping { string text = 1; }`
+fn write_descriptor_set(name: &str) -> Result<String> {
+    let set = FileDescriptorSet {
+        file: vec![FileDescriptorProto {
+            name: Some("echo.proto".to_string()),
+            package: Some("echo".to_string()),
+            syntax: Some("proto3".to_string()),
+            message_type: vec![DescriptorProto {
+                name: Some("Ping".to_string()),
+                field: vec![FieldDescriptorProto {
+                    name: Some("text".to_string()),
+                    number: Some(1),
+                    label: Some(Label::Optional as i32),
+                    r#type: Some(Type::String as i32),
+                    json_name: Some("text".to_string()),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            service: vec![ServiceDescriptorProto {
+                name: Some("Echo".to_string()),
+                method: vec![MethodDescriptorProto {
+                    name: Some("Say".to_string()),
+                    input_type: Some(".echo.Ping".to_string()),
+                    output_type: Some(".echo.Ping".to_string()),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }],
+    };
+    let path = std::env::temp_dir().join(format!("netdiff-{}-{}.pb", name, std::process::id()));
+    std::fs::write(&path, set.encode_to_vec())?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// echoes the request frames back with `grpc-status: 0` in the trailers
+async fn echo(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let mut body = req.into_body();
+    let mut frames = vec![];
+    while let Some(Ok(chunk)) = body.data().await {
+        frames.extend_from_slice(&chunk);
+    }
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        sender.send_data(frames.into()).await?;
+        let mut trailers = HeaderMap::new();
+        trailers.insert("grpc-status", "0".parse().unwrap());
+        sender.send_trailers(trailers).await
+    });
+    Ok(Response::builder()
+        .header("content-type", "application/grpc")
+        .body(body)
+        .unwrap())
+}
+
+fn echo_server() -> SocketAddr {
+    let make = make_service_fn(|_| async { Ok::<_, Infallible>(service_fn(echo)) });
+    let server = Server::bind(&"127.0.0.1:0".parse().unwrap())
+        .http2_only(true)
+        .serve(make);
+    let addr = server.local_addr();
+    tokio::spawn(server);
+    addr
+}
+
+fn profile_yaml(addr: SocketAddr, descriptor_set: &str, text: &str, extra: &str) -> String {
+    format!(
+        "    url: http://{}\n    grpc:\n      descriptor_set: {}\n      method: echo.Echo/Say\n      message: {{ text: {} }}\n{}",
+        addr, descriptor_set, text, extra
+    )
+}
+
+#[tokio::test]
+async fn grpc_profiles_call_an_in_process_server() -> Result<()> {
+    let addr = echo_server();
+    let descriptor_set = write_descriptor_set("call")?;
+    let config = RequestConfig::from_yaml(&format!(
+        "echo:\n{}",
+        profile_yaml(addr, &descriptor_set, "hi", "")
+    ))?;
+    let profile = config.get_profile("echo").unwrap();
+
+    let output = profile
+        .invoke(&ExtraArgs::default(), &ResponseProfile::default())
+        .await?;
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines[0], "HTTP/2.0 200 OK");
+    assert_eq!(lines[1], "grpc-status: 0 OK");
+    assert!(output.contains(r#""text": "hi""#), "{}", output);
+
+    let extra = ExtraArgs {
+        body: vec![("text".to_string(), "bye".to_string())],
+        ..ExtraArgs::default()
+    };
+    let output = profile.invoke(&extra, &ResponseProfile::default()).await?;
+    assert!(output.contains(r#""text": "bye""#), "{}", output);
+
+    let query = ExtraArgs {
+        query: vec![("a".to_string(), "1".to_string())],
+        ..ExtraArgs::default()
+    };
+    assert!(profile
+        .invoke(&query, &ResponseProfile::default())
+        .await
+        .is_err());
+
+    let config = DiffConfig::from_yaml(&format!(
+        "echo:\n  req1:\n{}  req2:\n{}",
+        profile_yaml(addr, &descriptor_set, "a", ""),
+        profile_yaml(addr, &descriptor_set, "b", ""),
+    ))?;
+    let result = config
+        .get_profile("echo")
+        .unwrap()
+        .diff(ExtraArgs::default())
+        .await?;
+    assert!(result.output.contains("\"a\"") && result.output.contains("\"b\""));
+    std::fs::remove_file(&descriptor_set)?;
+    Ok(())
+}
+
+#[test]
+fn grpc_profiles_reject_what_they_cant_apply() -> Result<()> {
+    let addr: SocketAddr = "127.0.0.1:50051".parse()?;
+    let descriptor_set = write_descriptor_set("reject")?;
+    let config = |extra: &str| {
+        RequestConfig::from_yaml(&format!(
+            "echo:\n{}",
+            profile_yaml(addr, &descriptor_set, "hi", extra)
+        ))
+    };
+    assert!(config("    timeout: 1000\n").is_ok());
+    assert!(config("    retries: 2\n").is_err());
+    assert!(config("    proxy: http://127.0.0.1:8080\n").is_err());
+    assert!(config("    tls:\n      insecure: true\n").is_err());
+    assert!(config("    params:\n      a: 1\n").is_err());
+    assert!(config("    expect:\n      status: 200\n").is_err());
+
+    let res = |res: &str| {
+        DiffConfig::from_yaml(&format!(
+            "echo:\n  req1:\n{}  req2:\n{}  res:\n{}",
+            profile_yaml(addr, &descriptor_set, "a", ""),
+            profile_yaml(addr, &descriptor_set, "b", ""),
+            res
+        ))
+    };
+    assert!(res("    skip_headers: [date]\n    skip_body: [id]\n").is_ok());
+    assert!(res("    timing: true\n").is_err());
+    assert!(res("    max_latency_ratio: 1.5\n").is_err());
+    assert!(res("    max_body_size: 10\n").is_err());
+
+    // the config-wide retries don't reach grpc profiles
+    let config = RequestConfig::from_yaml(&format!(
+        "defaults:\n  retries: 2\n  timeout: 1000\necho:\n{}",
+        profile_yaml(addr, &descriptor_set, "hi", "")
+    ))?;
+    assert_eq!(
+        config.get_profile("echo").unwrap().retry.timeout,
+        Some(1000)
+    );
+    std::fs::remove_file(&descriptor_set)?;
+    Ok(())
+}
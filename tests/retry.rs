@@ -0,0 +1,79 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use anyhow::Result;
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Response, Server, StatusCode,
+};
+use netdiff::{ExtraArgs, LoadConfig, RequestConfig};
+
+/// answers 503 to the first `failures` requests and 200 afterwards
+fn flaky_server(failures: usize) -> (SocketAddr, Arc<AtomicUsize>) {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let make = make_service_fn(move |_| {
+        let counter = counter.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |_req| {
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                let status = if n < failures {
+                    StatusCode::SERVICE_UNAVAILABLE
+                } else {
+                    StatusCode::OK
+                };
+                async move {
+                    Ok::<_, Infallible>(
+                        Response::builder()
+                            .status(status)
+                            .body(Body::from("{}"))
+                            .unwrap(),
+                    )
+                }
+            }))
+        }
+    });
+    let server = Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(make);
+    let addr = server.local_addr();
+    tokio::spawn(server);
+    (addr, hits)
+}
+
+fn config(addr: SocketAddr, retries: u32) -> Result<RequestConfig> {
+    RequestConfig::from_yaml(&format!(
+        "flaky:\n  url: http://{}/\n  retries: {}\n  backoff: {{ initial: 1 }}\n",
+        addr, retries
+    ))
+}
+
+#[tokio::test]
+async fn retries_until_the_status_is_not_retried() -> Result<()> {
+    let (addr, hits) = flaky_server(2);
+    let config = config(addr, 3)?;
+    let res = config
+        .get_profile("flaky")
+        .unwrap()
+        .send(&ExtraArgs::default())
+        .await?;
+    assert_eq!(res.response().status(), StatusCode::OK);
+    assert_eq!(res.attempts(), 3);
+    assert_eq!(hits.load(Ordering::SeqCst), 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn the_last_attempt_is_returned_when_retries_run_out() -> Result<()> {
+    let (addr, hits) = flaky_server(5);
+    let config = config(addr, 1)?;
+    let res = config
+        .get_profile("flaky")
+        .unwrap()
+        .send(&ExtraArgs::default())
+        .await?;
+    assert_eq!(res.response().status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(res.attempts(), 2);
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use anyhow::Result;
+use hyper::{
+    server::conn::Http,
+    service::{make_service_fn, service_fn},
+    Body, Response, Server,
+};
+use netdiff::{DiffConfig, ExtraArgs, LoadConfig, RequestConfig};
+use rustls::{Certificate, PrivateKey, ServerConfig, SupportedProtocolVersion};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+
+const CERT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/localhost.crt");
+const KEY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/localhost.key");
+
+async fn hello() -> Result<Response<Body>, Infallible> {
+    Ok(Response::new(Body::from("hello")))
+}
+
+/// a https server for `localhost` speaking only `version`
+async fn tls_server(version: &'static SupportedProtocolVersion) -> Result<SocketAddr> {
+    let certs = rustls_pemfile::certs(&mut std::fs::read(CERT)?.as_slice())?;
+    let key = rustls_pemfile::pkcs8_private_keys(&mut std::fs::read(KEY)?.as_slice())?;
+    let config = ServerConfig::builder()
+        .with_safe_default_cipher_suites()
+        .with_safe_default_kx_groups()
+        .with_protocol_versions(&[version])?
+        .with_no_client_auth()
+        .with_single_cert(
+            certs.into_iter().map(Certificate).collect(),
+            PrivateKey(key[0].clone()),
+        )?;
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                if let Ok(stream) = acceptor.accept(stream).await {
+                    let service = service_fn(|_| hello());
+                    let _ = Http::new().serve_connection(stream, service).await;
+                }
+            });
+        }
+    });
+    Ok(addr)
+}
+
+fn http_server() -> SocketAddr {
+    let make = make_service_fn(|_| async { Ok::<_, Infallible>(service_fn(|_| hello())) });
+    let server = Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(make);
+    let addr = server.local_addr();
+    tokio::spawn(server);
+    addr
+}
+
+/// the rows of the timing table after the diff of `url` against itself
+async fn timing_rows(url: &str, tls: &str) -> Result<Vec<String>> {
+    let req = format!("    url: {}\n{}", url, tls);
+    let config = DiffConfig::from_yaml(&format!(
+        "a:\n  req1:\n{}  req2:\n{}  res:\n    timing: true\n",
+        req, req
+    ))?;
+    let result = config
+        .get_profile("a")
+        .unwrap()
+        .diff(ExtraArgs::default())
+        .await?;
+    let table = result
+        .output
+        .split("======== timing ========\n")
+        .nth(1)
+        .unwrap_or_default();
+    Ok(table
+        .lines()
+        .skip(1)
+        .map(|line| {
+            line.split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        })
+        .collect())
+}
+
+#[tokio::test]
+async fn https_requests_time_the_connect_and_the_handshake() -> Result<()> {
+    for version in [&rustls::version::TLS13, &rustls::version::TLS12] {
+        let addr = tls_server(version).await?;
+        let rows = timing_rows(
+            &format!("https://localhost:{}/", addr.port()),
+            &format!("    tls:\n      ca_cert: {}\n", CERT),
+        )
+        .await?;
+        assert_eq!(
+            rows,
+            ["dns", "connect", "tls", "ttfb", "total", "size"],
+            "{:?}",
+            version
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn plain_http_has_no_connect_or_tls_phase() -> Result<()> {
+    let addr = http_server();
+    let rows = timing_rows(&format!("http://127.0.0.1:{}/", addr.port()), "").await?;
+    assert_eq!(rows, ["ttfb", "total", "size"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn certificates_are_checked_unless_insecure() -> Result<()> {
+    let addr = tls_server(&rustls::version::TLS13).await?;
+    let send = |tls: &str| {
+        let yaml = format!("a:\n  url: https://localhost:{}/\n{}", addr.port(), tls);
+        async move {
+            let config = RequestConfig::from_yaml(&yaml)?;
+            config
+                .get_profile("a")
+                .unwrap()
+                .send(&ExtraArgs::default())
+                .await
+        }
+    };
+    assert!(send("").await.is_err());
+    assert!(send("  tls:\n    insecure: true\n").await.is_ok());
+    assert!(send(&format!("  tls:\n    ca_cert: {}\n", CERT))
+        .await
+        .is_ok());
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use anyhow::Result;
+use netdiff::{DiffConfig, ExtraArgs, LoadConfig};
+use tokio::net::TcpListener;
+
+#[allow(dead_code)]
+#[path = "../examples/ws_echo.rs"]
+mod ws_echo;
+
+async fn echo_server() -> Result<String> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(ws_echo::serve(listener));
+    Ok(format!("ws://{}/", addr))
+}
+
+#[tokio::test]
+async fn websocket_profiles_diff_the_echoed_messages() -> Result<()> {
+    let url = echo_server().await?;
+    let config = DiffConfig::from_yaml(&format!(
+        r#"
+echo:
+  req1:
+    url: {url}
+    websocket:
+      send: [hello, {{"id": 1, "at": 1}}]
+      count: 2
+  req2:
+    url: {url}
+    websocket:
+      send: [hello, {{"id": 1, "at": 2}}]
+      count: 2
+  res:
+    skip_body: [at]
+"#
+    ))?;
+    let profile = config.get_profile("echo").unwrap();
+
+    let output = profile
+        .req1
+        .converse(&ExtraArgs::default(), &profile.res.skip_body)
+        .await?;
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines[0], "HTTP/1.1 101 Switching Protocols");
+    assert!(lines.contains(&"> hello"));
+    assert!(lines.contains(&"< hello"));
+    assert!(!output.contains("\"at\""));
+
+    let result = profile.diff(ExtraArgs::default()).await?;
+    assert_eq!(result.output, "");
+    assert!(result.failures.is_empty());
+    Ok(())
+}
+
+#[test]
+fn websocket_profiles_reject_what_they_cant_apply() {
+    let config = |extra: &str| {
+        DiffConfig::from_yaml(&format!(
+            "ws:\n  req1:\n    url: ws://127.0.0.1:9001/\n    websocket: {{}}\n{}  req2:\n    url: ws://127.0.0.1:9001/\n    websocket: {{}}\n",
+            extra
+        ))
+    };
+    assert!(config("").is_ok());
+    assert!(config("    proxy: http://127.0.0.1:8080\n").is_err());
+    assert!(config("    tls:\n      insecure: true\n").is_err());
+    assert!(config("    expect:\n      status: 101\n").is_err());
+    assert!(config("    retries: 2\n").is_err());
+
+    let res = |res: &str| {
+        DiffConfig::from_yaml(&format!(
+            "ws:\n  req1:\n    url: ws://127.0.0.1:9001/\n    websocket: {{}}\n  req2:\n    url: ws://127.0.0.1:9001/\n    websocket: {{}}\n  res:\n{}",
+            res
+        ))
+    };
+    assert!(res("    skip_body: [id]\n").is_ok());
+    assert!(res("    timing: true\n").is_err());
+    assert!(res("    max_latency_ratio: 1.5\n").is_err());
+    assert!(res("    skip_headers: [date]\n").is_err());
+
+    // the config-wide retries don't reach websocket profiles
+    let config = DiffConfig::from_yaml(
+        "defaults:\n  retries: 2\n  timeout: 1000\nws:\n  req1:\n    url: ws://127.0.0.1:9001/\n    websocket: {}\n  req2:\n    url: ws://127.0.0.1:9001/\n    websocket: {}\n",
+    )
+    .unwrap();
+    let retry = &config.get_profile("ws").unwrap().req1.retry;
+    assert_eq!((retry.retries, retry.timeout), (None, Some(1000)));
+}